@@ -1,5 +1,3 @@
-use tokio;
-use std::path::PathBuf;
 
 // Import from parent crate
 #[path = "../data/mod.rs"]
@@ -10,8 +8,14 @@ mod models;
 mod yahoo_data;
 #[path = "../alternative_data.rs"]
 mod alternative_data;
+#[path = "../bitcoin_history.rs"]
+mod bitcoin_history;
+#[path = "../csv_data.rs"]
+mod csv_data;
 
-use data::get_historical_data;
+use data::{get_historical_data, source_ranges, LoadProgress};
+use data::cache::PriceCache;
+use models::Currency;
 
 #[tokio::main]
 async fn main() {
@@ -20,29 +24,42 @@ async fn main() {
     println!("Waiting 60 seconds to avoid rate limiting...");
     tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
     
-    let data = get_historical_data();
+    let cad = Currency::from_code("CAD").unwrap();
+    let data = get_historical_data(Some(&PriceCache::default_location()), &[cad], &|event| match event {
+        LoadProgress::Fetching { asset, source } => println!("[progress] {:?}: fetching from {}", asset, source),
+        LoadProgress::Retrying { asset, source } => println!("[progress] {:?}: retrying with {}", asset, source),
+        LoadProgress::Loaded { asset, data } => println!("[progress] {:?}: loaded {} points", asset, data.len()),
+        LoadProgress::ExchangeRates { currency, rates } => println!("[progress] USD/{}: loaded {} rates", currency.code, rates.len()),
+        LoadProgress::Failed { asset, error } => println!("[progress] {:?}: failed ({})", asset, error),
+        LoadProgress::Finished => println!("[progress] finished"),
+    });
     
     println!("\n=== Data Summary ===");
-    for (asset, prices) in &data {
+    for (asset, prices) in &data.prices {
         println!("{:?}: {} data points", asset, prices.len());
         
         // Check CAD prices
-        let with_cad = prices.iter().filter(|p| p.price_cad.is_some()).count();
+        let with_cad = prices.iter().filter(|p| data.fx.rate(cad, p.date).is_some()).count();
         println!("  - With CAD prices: {}", with_cad);
         
+        // Show which source each range came from
+        for (source, from, to) in source_ranges(prices) {
+            println!("  - {} to {}: {}", from, to, source.unwrap_or("derived"));
+        }
+        
         // Show first and last prices
         if let Some(first) = prices.first() {
             println!("  - First: {} USD = {:.2}, CAD = {}", 
                 first.date, 
                 first.price_usd, 
-                first.price_cad.map(|c| format!("{:.2}", c)).unwrap_or("N/A".to_string())
+                data.fx.convert(first.price_usd, cad, first.date).map(|c| format!("{:.2}", c)).unwrap_or("N/A".to_string())
             );
         }
         if let Some(last) = prices.last() {
             println!("  - Last: {} USD = {:.2}, CAD = {}", 
                 last.date, 
                 last.price_usd,
-                last.price_cad.map(|c| format!("{:.2}", c)).unwrap_or("N/A".to_string())
+                data.fx.convert(last.price_usd, cad, last.date).map(|c| format!("{:.2}", c)).unwrap_or("N/A".to_string())
             );
         }
     }
@@ -1,6 +1,32 @@
 use chrono::{DateTime, Utc, NaiveDate};
+use async_trait::async_trait;
 use anyhow::Result;
+use crate::models::Asset;
 use crate::data::PriceData;
+use crate::data::source::{PriceSource, SourceCoverage};
+
+/// CoinDesk Bitcoin Price Index, the longest free BTC history
+pub struct CoinDeskSource;
+
+#[async_trait]
+impl PriceSource for CoinDeskSource {
+    fn name(&self) -> &'static str {
+        "CoinDesk"
+    }
+    
+    fn coverage(&self, asset: Asset) -> Option<SourceCoverage> {
+        // The CoinDesk price index only covers Bitcoin
+        let ticker = asset.source("coindesk").filter(|_| asset == Asset::BITCOIN)?;
+        Some(SourceCoverage { earliest: ticker.since, synthetic: false })
+    }
+    
+    async fn fetch_asset(&self, asset: Asset, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<PriceData>> {
+        if asset != Asset::BITCOIN {
+            return Err(anyhow::anyhow!("CoinDesk only provides Bitcoin prices"));
+        }
+        fetch_extended_btc_history(start, end).await
+    }
+}
 
 /// Try to fetch extended Bitcoin history from CoinDesk
 pub async fn fetch_extended_btc_history(start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<PriceData>> {
@@ -20,7 +46,7 @@ pub async fn fetch_extended_btc_history(start: DateTime<Utc>, end: DateTime<Utc>
         start_str, end_str
     );
     
-    println!("Fetching extended BTC history from CoinDesk...");
+    eprintln!("Fetching extended BTC history from CoinDesk...");
     
     let response = client.get(&url)
         .header("User-Agent", "Mozilla/5.0")
@@ -48,7 +74,8 @@ pub async fn fetch_extended_btc_history(start: DateTime<Utc>, end: DateTime<Utc>
             data.push(PriceData {
                 date,
                 price_usd: price,
-                price_cad: None, // Will be calculated later
+                source: None,
+                ohlcv: None,
             });
         }
     }
@@ -56,31 +83,7 @@ pub async fn fetch_extended_btc_history(start: DateTime<Utc>, end: DateTime<Utc>
     // Sort by date
     data.sort_by_key(|p| p.date);
     
-    println!("Fetched {} days of historical BTC data from CoinDesk", data.len());
+    eprintln!("Fetched {} days of historical BTC data from CoinDesk", data.len());
     
     Ok(data)
 }
-
-/// Merge Yahoo and CoinDesk data, preferring Yahoo where available
-pub fn merge_btc_data(yahoo_data: Vec<PriceData>, coindesk_data: Vec<PriceData>) -> Vec<PriceData> {
-    use std::collections::HashMap;
-    
-    // Create a map of dates to prices, starting with CoinDesk data
-    let mut date_map: HashMap<NaiveDate, PriceData> = HashMap::new();
-    
-    // Add CoinDesk data first
-    for price in coindesk_data {
-        date_map.insert(price.date, price);
-    }
-    
-    // Override with Yahoo data where available (usually more accurate)
-    for price in yahoo_data {
-        date_map.insert(price.date, price);
-    }
-    
-    // Convert back to sorted vec
-    let mut merged: Vec<PriceData> = date_map.into_values().collect();
-    merged.sort_by_key(|p| p.date);
-    
-    merged
-}
\ No newline at end of file
@@ -0,0 +1,763 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use std::io::Write;
+use crate::models::{Asset, Currency, RepresentationMode};
+use crate::data::{get_historical_data, source_ranges, LoadProgress, MarketData, Ohlcv};
+use crate::data::cache::PriceCache;
+use crate::data::align::{AlignPolicy, Alignment, Calendar};
+use crate::data::resample::{self, Aggregation, Frequency};
+use crate::series::{self, ChartOptions, SeriesPoint, SeriesTable};
+use crate::export::{self, ExportFormat};
+use crate::report::HtmlReport;
+use crate::render::{ChartImage, ImageFormat};
+use crate::stats::{self, CorrelationMatrix, LineStats};
+use crate::dca::{self, DcaPlan};
+use crate::portfolio::{self, Portfolio};
+
+const USAGE: &str = "\
+Usage: asset_price_watcher [COMMAND] [OPTIONS]
+
+Without a command the chart window opens.
+
+Commands:
+  series    Bitcoin, USD and currency prices of one asset over time
+  compare   Bitcoin prices of several assets side by side
+  fetch     Update the price cache and summarize what was fetched
+  export    Write the chart's numbers for one asset to a CSV, JSON or Parquet file
+  report    Write an interactive HTML (Plotly) chart of one asset
+  render    Draw the % change chart of one asset to PNG and/or SVG
+  bars      Open/high/low/close bars of one asset, e.g. weekly candles
+  stats     Return, CAGR, volatility, drawdown and Sharpe of BTC, USD and each
+            currency, measured in units of one asset
+  rolling   Rolling correlation, beta and ratio volatility of BTC against one asset
+  correlations
+            Return correlations between every pair of assets and baskets
+  dca       Dollar-cost average into BTC, one asset, USD or cash, valued in
+            units of that asset
+  portfolio Value the holdings in the portfolio file over time, in a currency
+            and in units of one asset
+  serve     Serve prices and purchasing-power series as JSON over HTTP
+  assets    List asset and basket ids
+
+Options:
+  --asset ID             Asset or basket for `series`, `export`, `report`, `render`,
+                         `bars`, `stats`, `rolling`, `dca` and `portfolio` (default:
+                         first basket)
+  --against ID,ID        Assets and baskets for `compare` (default: all baskets) and
+                         `correlations` (default: all), or drawn next to the asset in
+                         `report` and `render`
+  --currencies CAD,EUR   Currencies next to USD, and kept as cash by `dca` (default:
+                         $CURRENCIES or CAD)
+  --mode price|units     Price per unit (default) or units per BTC/currency
+  --align POLICY         How asset prices are matched to dates they have no price on:
+                         nearest (default), exact, ffill, bfill or linear
+  --max-days N           Furthest an asset price may be from the date it is used
+                         for (default: 30)
+  --calendar all|trading|carry
+                         Days the asset is compared with Bitcoin on: every day
+                         (default, prices matched by --align), only days it traded,
+                         or every day carrying its last price forward, with those
+                         days marked in exports, reports and images
+  --frequency F          Resample to daily (default), weekly, monthly, quarterly or
+                         yearly values, dated on the first day of each period
+  --aggregate last|mean|ohlc
+                         Value of each period: last close (default), average close,
+                         or last close plus open/high/low/close bars
+  --amount N             Contribution for `dca` (default: 100)
+  --currency CODE        Currency `dca` contributes in and `portfolio` is valued in
+                         (default: USD)
+  --every F              How often `dca` contributes: daily, weekly, monthly (default),
+                         quarterly or yearly
+  --from YYYY-MM-DD      First date to print
+  --to YYYY-MM-DD        Last date to print
+  --window DAYS,DAYS     Trailing windows for `rolling` (default: 90), or the days up
+                         to --to (default: the last price) for `correlations`
+  --percent              Print % change from the first printed date, like the chart
+  --format table|csv|json  Output format (default: table)
+  --output PATH          File for `export`, `report` and `render`; for `export` the
+                         extension picks the format unless --format is given (csv, json
+                         or parquet), `render` writes both .png and .svg without one
+  --size WIDTHxHEIGHT    Image size for `render` (default: 1200x700)
+  --plotly-js PATH       Inline this plotly.min.js in the report instead of loading
+                         it from the CDN
+  --host ADDRESS         Address `serve` listens on (default: 127.0.0.1)
+  --port PORT            Port `serve` listens on (default: 8080)
+  --portfolio PATH       Holdings for `portfolio` (default: $PORTFOLIO or portfolio.json)
+  --full-refresh         Ignore cached prices and refetch all history
+  --assets PATH          Asset config to use instead of the built-in one
+";
+
+/// How the headless commands print their results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Table,
+    Csv,
+    Json,
+}
+
+impl OutputFormat {
+    fn parse(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "table" => Ok(Self::Table),
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(anyhow::anyhow!("Unknown format {}, expected table, csv or json", name)),
+        }
+    }
+}
+
+/// Run a headless command if `args` (including the program name) start with one;
+/// `None` means the GUI should start
+pub fn run(args: &[String]) -> Option<Result<()>> {
+    let command = args.get(1)?.as_str();
+    let options = &args[2..];
+    let result = match command {
+        "series" => series_command(options),
+        "compare" => compare_command(options),
+        "fetch" => fetch_command(options),
+        "export" => export_command(options),
+        "report" => report_command(options),
+        "render" => render_command(options),
+        "bars" => bars_command(options),
+        "stats" => stats_command(options),
+        "rolling" => rolling_command(options),
+        "correlations" => correlations_command(options),
+        "dca" => dca_command(options),
+        "portfolio" => portfolio_command(options),
+        "serve" => serve_command(options),
+        "assets" => {
+            list_assets();
+            Ok(())
+        }
+        "help" | "--help" | "-h" => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+        _ => return None,
+    };
+    Some(result)
+}
+
+fn option<'a>(options: &'a [String], name: &str) -> Option<&'a str> {
+    options.iter()
+        .position(|arg| arg == name)
+        .and_then(|i| options.get(i + 1))
+        .map(String::as_str)
+}
+
+fn flag(options: &[String], name: &str) -> bool {
+    options.iter().any(|arg| arg == name)
+}
+
+fn date_option(options: &[String], name: &str) -> Result<Option<NaiveDate>> {
+    option(options, name)
+        .map(|text| NaiveDate::parse_from_str(text, "%Y-%m-%d").with_context(|| format!("Invalid {} date {}", name, text)))
+        .transpose()
+}
+
+fn parse_asset(id: &str) -> Result<Asset> {
+    Asset::from_id(id).ok_or_else(|| anyhow::anyhow!("Unknown asset {}, run `asset_price_watcher assets` for the list", id))
+}
+
+// `--asset`, defaulting to the first basket
+fn asset_option(options: &[String]) -> Result<Asset> {
+    match option(options, "--asset") {
+        Some(id) => parse_asset(id),
+        None => Asset::baskets().next().ok_or_else(|| anyhow::anyhow!("No baskets configured, pass --asset")),
+    }
+}
+
+fn asset_list(list: &str) -> Result<Vec<Asset>> {
+    list.split(',').map(str::trim).filter(|id| !id.is_empty()).map(parse_asset).collect()
+}
+
+fn currencies(options: &[String]) -> Vec<Currency> {
+    let list = option(options, "--currencies")
+        .map(str::to_string)
+        .or_else(|| std::env::var("CURRENCIES").ok())
+        .unwrap_or_else(|| "CAD".to_string());
+    Currency::parse_list(&list)
+}
+
+fn mode(options: &[String]) -> Result<RepresentationMode> {
+    let name = option(options, "--mode").unwrap_or("price");
+    RepresentationMode::parse(name).ok_or_else(|| anyhow::anyhow!("Unknown mode {}, expected price or units", name))
+}
+
+fn alignment(options: &[String]) -> Result<Alignment> {
+    let mut alignment = Alignment::default();
+    if let Some(name) = option(options, "--align") {
+        alignment.policy = AlignPolicy::parse(name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown alignment {}, expected exact, ffill, bfill, nearest or linear", name))?;
+    }
+    if let Some(days) = option(options, "--max-days") {
+        alignment.max_days = days.parse().with_context(|| format!("Invalid --max-days {}", days))?;
+        if alignment.max_days < 0 {
+            return Err(anyhow::anyhow!("Invalid --max-days {}, expected 0 or more days", days));
+        }
+    }
+    if let Some(name) = option(options, "--calendar") {
+        alignment.calendar = Calendar::parse(name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown calendar {}, expected all, trading or carry", name))?;
+    }
+    Ok(alignment)
+}
+
+fn resampling(options: &[String]) -> Result<(Frequency, Aggregation)> {
+    let frequency = match option(options, "--frequency") {
+        Some(name) => Frequency::parse(name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown frequency {}, expected daily, weekly, monthly, quarterly or yearly", name))?,
+        None => Frequency::Daily,
+    };
+    let aggregation = match option(options, "--aggregate") {
+        Some(name) => Aggregation::parse(name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown aggregation {}, expected last, mean or ohlc", name))?,
+        None => Aggregation::Last,
+    };
+    Ok((frequency, aggregation))
+}
+
+// Load prices through the cache, reporting progress on stderr so stdout stays parseable
+fn load(options: &[String], currencies: &[Currency], alignment: Alignment, max_age: Option<chrono::Duration>) -> MarketData {
+    let mut cache = PriceCache::default_location().with_full_refresh(flag(options, "--full-refresh"));
+    if let Some(max_age) = max_age {
+        cache = cache.with_max_age(max_age);
+    }
+    let mut data = get_historical_data(Some(&cache), currencies, &|event| match event {
+        LoadProgress::Loaded { asset, data } => eprintln!("Loaded {:?}: {} points", asset, data.len()),
+        LoadProgress::Failed { asset, error } => eprintln!("Failed to load {:?}: {}", asset, error),
+        _ => {}
+    });
+    // Baskets only depend on the policy, the calendar is applied when comparing with Bitcoin
+    let alignment = Alignment { calendar: Calendar::AllDays, ..alignment };
+    if alignment != Alignment::default() {
+        data.rebuild_baskets(alignment);
+    }
+    data
+}
+
+// Apply the shared --from/--to/--percent/--format options and print
+fn print_table(options: &[String], table: SeriesTable) -> Result<()> {
+    let format = option(options, "--format").map(OutputFormat::parse).transpose()?.unwrap_or(OutputFormat::Table);
+    let mut table = table.between(date_option(options, "--from")?, date_option(options, "--to")?);
+    if flag(options, "--percent") {
+        table = table.percent_change();
+    }
+    if table.rows.is_empty() {
+        return Err(anyhow::anyhow!("No data for the requested dates"));
+    }
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    match format {
+        OutputFormat::Table => write_text(&mut out, &table)?,
+        OutputFormat::Csv => export::write_csv(&mut out, &table)?,
+        OutputFormat::Json => export::write_json(&mut out, &table)?,
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn format_value(value: f64) -> String {
+    // Enough decimals to show small BTC prices, fewer for large ones
+    let decimals = if value.abs() >= 1000.0 { 2 } else if value.abs() >= 1.0 { 4 } else { 8 };
+    format!("{:.*}", decimals, value)
+}
+
+fn write_text(out: &mut impl Write, table: &SeriesTable) -> Result<()> {
+    let width = table.columns.iter().map(|c| c.len()).max().unwrap_or(0).max(16);
+    write!(out, "{:<10}", "date")?;
+    for column in &table.columns {
+        write!(out, "  {:>width$}", column, width = width)?;
+    }
+    writeln!(out)?;
+    for (date, values) in &table.rows {
+        write!(out, "{:<10}", date.format("%Y-%m-%d"))?;
+        for value in values {
+            let text = value.map(format_value).unwrap_or_else(|| "-".to_string());
+            write!(out, "  {:>width$}", text, width = width)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+// BTC, USD and currency lines of one asset, as drawn in the chart
+fn series_command(options: &[String]) -> Result<()> {
+    let asset = asset_option(options)?;
+    let mode = mode(options)?;
+    let alignment = alignment(options)?;
+    let (frequency, aggregation) = resampling(options)?;
+    let currencies = currencies(options);
+    let data = load(options, &currencies, alignment, None).resampled(frequency, aggregation);
+
+    let asset_data = data.prices.get(&asset)
+        .ok_or_else(|| anyhow::anyhow!("No price data for {:?}", asset))?;
+    let btc_data = data.prices.get(&Asset::BITCOIN)
+        .ok_or_else(|| anyhow::anyhow!("No Bitcoin price data"))?;
+
+    let mut columns: Vec<(String, Vec<SeriesPoint>)> = vec![
+        ("BTC".to_string(), series::btc_values(btc_data, asset_data, mode, alignment)),
+        ("USD".to_string(), series::usd_values(asset_data, mode)),
+    ];
+    for &currency in &currencies {
+        if data.fx.has(currency) {
+            columns.push((currency.code.to_string(), series::currency_values(asset_data, &data.fx, currency, mode)));
+        }
+    }
+
+    eprintln!("{} ({}), {}", asset.name(), asset.id(), match mode {
+        RepresentationMode::PricePerUnit => format!("price per {}", asset.unit()),
+        RepresentationMode::UnitsPerCurrency => format!("{} per BTC / currency unit", asset.unit()),
+    });
+    print_table(options, SeriesTable::from_series(columns))
+}
+
+// Bitcoin line of several assets, one column each
+fn compare_command(options: &[String]) -> Result<()> {
+    let assets: Vec<Asset> = match option(options, "--against") {
+        Some(list) => asset_list(list)?,
+        None => Asset::baskets().collect(),
+    };
+    if assets.is_empty() {
+        return Err(anyhow::anyhow!("Nothing to compare, pass --against"));
+    }
+    let mode = mode(options)?;
+    let alignment = alignment(options)?;
+    let (frequency, aggregation) = resampling(options)?;
+    let data = load(options, &[], alignment, None).resampled(frequency, aggregation);
+
+    let btc_data = data.prices.get(&Asset::BITCOIN)
+        .ok_or_else(|| anyhow::anyhow!("No Bitcoin price data"))?;
+    let columns = assets.iter()
+        .map(|asset| {
+            let points = data.prices.get(asset)
+                .map(|asset_data| series::btc_values(btc_data, asset_data, mode, alignment))
+                .unwrap_or_default();
+            if points.is_empty() {
+                eprintln!("No data for {:?}", asset);
+            }
+            (asset.id().to_string(), points)
+        })
+        .collect();
+
+    print_table(options, SeriesTable::from_series(columns))
+}
+
+// Chart numbers for one asset (raw prices, values and % change from --from) to a file
+fn export_command(options: &[String]) -> Result<()> {
+    let path = option(options, "--output")
+        .map(std::path::PathBuf::from)
+        .ok_or_else(|| anyhow::anyhow!("Pass --output PATH"))?;
+    let format = match option(options, "--format") {
+        Some(name) => ExportFormat::parse(name),
+        None => ExportFormat::from_path(&path),
+    }
+    .ok_or_else(|| anyhow::anyhow!("Unknown export format, use csv, json or parquet"))?;
+    let asset = asset_option(options)?;
+    let mode = mode(options)?;
+    let alignment = alignment(options)?;
+    let (frequency, aggregation) = resampling(options)?;
+    let currencies = currencies(options);
+    let data = load(options, &currencies, alignment, None).resampled(frequency, aggregation);
+
+    let chart = ChartOptions { mode, alignment, from: date_option(options, "--from")?, to: date_option(options, "--to")? };
+    let table = series::chart_table(&data.prices, &data.fx, asset, &currencies, &[], chart);
+    if table.rows.is_empty() {
+        return Err(anyhow::anyhow!("No data for {:?} in the requested dates", asset));
+    }
+    export::export(&table, &path, format)?;
+    eprintln!("Wrote {} rows to {}", table.rows.len(), path.display());
+    Ok(())
+}
+
+// Interactive HTML version of the chart for one asset
+fn report_command(options: &[String]) -> Result<()> {
+    let path = option(options, "--output").unwrap_or("report.html");
+    let asset = asset_option(options)?;
+    let compared = option(options, "--against").map(asset_list).transpose()?.unwrap_or_default();
+    let plotly_js = option(options, "--plotly-js")
+        .map(|path| std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path)))
+        .transpose()?;
+    let mode = mode(options)?;
+    let alignment = alignment(options)?;
+    let (frequency, aggregation) = resampling(options)?;
+    let currencies = currencies(options);
+    let data = load(options, &currencies, alignment, None).resampled(frequency, aggregation);
+
+    if data.prices.get(&asset).is_none_or(|prices| prices.is_empty()) {
+        return Err(anyhow::anyhow!("No price data for {:?}", asset));
+    }
+    let mut report = HtmlReport::new(&data.prices, &data.fx, asset)
+        .with_currencies(&currencies)
+        .with_compared(&compared)
+        .with_mode(mode)
+        .with_alignment(alignment)
+        .between(date_option(options, "--from")?, date_option(options, "--to")?);
+    if let Some(script) = plotly_js {
+        report = report.with_plotly_js(script);
+    }
+    report.write(std::path::Path::new(path))?;
+    eprintln!("Wrote {}", path);
+    Ok(())
+}
+
+fn size_option(options: &[String]) -> Result<(u32, u32)> {
+    let Some(text) = option(options, "--size") else {
+        return Ok((1200, 700));
+    };
+    text.split_once('x')
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+        .filter(|&(width, height)| width > 0 && height > 0)
+        .ok_or_else(|| anyhow::anyhow!("Invalid --size {}, expected WIDTHxHEIGHT", text))
+}
+
+// Static image of the chart, e.g. for newsletters generated on a server
+fn render_command(options: &[String]) -> Result<()> {
+    let path = std::path::PathBuf::from(option(options, "--output").unwrap_or("chart"));
+    let outputs: Vec<(std::path::PathBuf, ImageFormat)> = match ImageFormat::from_path(&path) {
+        Some(format) => vec![(path, format)],
+        None if path.extension().is_some() => return Err(anyhow::anyhow!("Unknown image format, use .png or .svg")),
+        None => ImageFormat::all().into_iter().map(|format| (path.with_extension(format.extension()), format)).collect(),
+    };
+    let asset = asset_option(options)?;
+    let compared = option(options, "--against").map(asset_list).transpose()?.unwrap_or_default();
+    let size = size_option(options)?;
+    let mode = mode(options)?;
+    let alignment = alignment(options)?;
+    let (frequency, aggregation) = resampling(options)?;
+    let currencies = currencies(options);
+    let data = load(options, &currencies, alignment, None).resampled(frequency, aggregation);
+
+    let from = date_option(options, "--from")?;
+    let to = date_option(options, "--to")?;
+    let markers = data.prices.get(&Asset::BITCOIN)
+        .map(|prices| source_ranges(prices).into_iter()
+            .skip(1)
+            .map(|(source, date, _)| (date, source.unwrap_or("derived").to_string()))
+            .collect())
+        .unwrap_or_default();
+    let image = ChartImage {
+        title: series::chart_title(asset, mode),
+        lines: series::chart_lines(&data.prices, &data.fx, asset, &currencies, &compared, mode, alignment),
+        from,
+        to,
+        markers,
+        size,
+    };
+    for (path, format) in outputs {
+        image.write(&path, format)?;
+        eprintln!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+// Bars of one asset in USD at --frequency, built from its daily bars
+fn bars_command(options: &[String]) -> Result<()> {
+    let asset = asset_option(options)?;
+    let (frequency, _) = resampling(options)?;
+    let alignment = alignment(options)?;
+    let data = load(options, &[], alignment, None);
+
+    let prices = data.prices.get(&asset)
+        .ok_or_else(|| anyhow::anyhow!("No price data for {:?}", asset))?;
+    let bars: Vec<(NaiveDate, Ohlcv)> = resample::resample(prices, frequency, Aggregation::Ohlc).into_iter()
+        .filter_map(|p| Some((p.date, p.ohlcv?)))
+        .collect();
+    if bars.is_empty() {
+        return Err(anyhow::anyhow!("No open/high/low/close bars for {:?}, its source only provides closing prices", asset));
+    }
+    let column = |name: &str, value: fn(&Ohlcv) -> Option<f64>| {
+        (name.to_string(), bars.iter().filter_map(|(date, bar)| Some((*date, value(bar)?))).collect())
+    };
+    let columns: Vec<(String, Vec<SeriesPoint>)> = vec![
+        column("open", |bar| Some(bar.open)),
+        column("high", |bar| Some(bar.high)),
+        column("low", |bar| Some(bar.low)),
+        column("close", |bar| Some(bar.close)),
+        column("volume", |bar| bar.volume),
+    ];
+    eprintln!("{} ({}), {} bars in USD", asset.name(), asset.id(), frequency.label().to_lowercase());
+    print_table(options, SeriesTable::from_series(columns))
+}
+
+// Performance of holding BTC, dollars or each currency, in units of one asset
+fn stats_command(options: &[String]) -> Result<()> {
+    let asset = asset_option(options)?;
+    let format = option(options, "--format").map(OutputFormat::parse).transpose()?.unwrap_or(OutputFormat::Table);
+    let alignment = alignment(options)?;
+    let (frequency, aggregation) = resampling(options)?;
+    let from = date_option(options, "--from")?;
+    let to = date_option(options, "--to")?;
+    let currencies = currencies(options);
+    let data = load(options, &currencies, alignment, None).resampled(frequency, aggregation);
+
+    let stats = stats::purchasing_power_stats(&data.prices, &data.fx, asset, &currencies, alignment, from, to);
+    if stats.is_empty() {
+        return Err(anyhow::anyhow!("Not enough data for {:?} in the requested dates", asset));
+    }
+    eprintln!("{} ({}), in {} per BTC / currency unit", asset.name(), asset.id(), asset.unit());
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    match format {
+        OutputFormat::Table => write_stats(&mut out, &stats, frequency)?,
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(&mut out);
+            for line in &stats {
+                writer.serialize(line)?;
+            }
+            writer.flush()?;
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut out, &stats)?;
+            writeln!(out)?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+// Rolling statistics of Bitcoin against one asset, three columns per window
+fn rolling_command(options: &[String]) -> Result<()> {
+    let asset = asset_option(options)?;
+    let windows: Vec<i64> = match option(options, "--window") {
+        Some(list) => list.split(',')
+            .map(|days| days.trim().parse().ok().filter(|days| *days > 0)
+                .ok_or_else(|| anyhow::anyhow!("Invalid --window {}, expected days like 30,90,365", list)))
+            .collect::<Result<_>>()?,
+        None => vec![90],
+    };
+    let alignment = alignment(options)?;
+    let (frequency, aggregation) = resampling(options)?;
+    let data = load(options, &[], alignment, None).resampled(frequency, aggregation);
+
+    let asset_data = data.prices.get(&asset)
+        .ok_or_else(|| anyhow::anyhow!("No price data for {:?}", asset))?;
+    let btc_data = data.prices.get(&Asset::BITCOIN)
+        .ok_or_else(|| anyhow::anyhow!("No Bitcoin price data"))?;
+    let mut columns: Vec<(String, Vec<SeriesPoint>)> = Vec::new();
+    for window in windows {
+        let points = stats::rolling(btc_data, asset_data, alignment, window);
+        let column = |value: fn(&stats::RollingPoint) -> Option<f64>| -> Vec<SeriesPoint> {
+            points.iter().filter_map(|point| Some((point.date, value(point)?))).collect()
+        };
+        columns.push((format!("correlation_{}d", window), column(|p| p.correlation)));
+        columns.push((format!("beta_{}d", window), column(|p| p.beta)));
+        columns.push((format!("ratio_volatility_{}d", window), column(|p| p.ratio_volatility_pct)));
+    }
+
+    eprintln!("Bitcoin against {} ({}), log returns over trailing windows", asset.name(), asset.id());
+    print_table(options, SeriesTable::from_series(columns))
+}
+
+// Correlation matrix of returns over a date range, at the chosen frequency
+fn correlations_command(options: &[String]) -> Result<()> {
+    let assets: Vec<Asset> = match option(options, "--against") {
+        Some(list) => asset_list(list)?,
+        None => Asset::tracked().iter().copied().chain(Asset::baskets()).collect(),
+    };
+    let format = option(options, "--format").map(OutputFormat::parse).transpose()?.unwrap_or(OutputFormat::Table);
+    let (frequency, aggregation) = resampling(options)?;
+    let alignment = alignment(options)?;
+    let data = load(options, &[], alignment, None).resampled(frequency, aggregation);
+
+    let to = date_option(options, "--to")?;
+    let from = match option(options, "--window") {
+        Some(days) => {
+            let days: i64 = days.trim().parse().ok().filter(|days| *days > 0)
+                .ok_or_else(|| anyhow::anyhow!("Invalid --window {}, expected a number of days", days))?;
+            let end = to.or_else(|| assets.iter()
+                .filter_map(|asset| data.prices.get(asset)?.last().map(|p| p.date))
+                .max());
+            end.map(|end| end - chrono::Duration::days(days))
+        }
+        None => date_option(options, "--from")?,
+    };
+    let matrix = CorrelationMatrix::new(&data.prices, &assets, from, to);
+    if matrix.assets.len() < 2 {
+        return Err(anyhow::anyhow!("Not enough assets with prices in the requested dates"));
+    }
+
+    eprintln!("{} log returns, {} assets", frequency.label(), matrix.assets.len());
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    match format {
+        OutputFormat::Table => {
+            let width = matrix.assets.iter().map(|a| a.id().len()).max().unwrap_or(0).max(6);
+            write!(out, "{:<width$}", "", width = width)?;
+            for asset in &matrix.assets {
+                write!(out, "  {:>width$}", asset.id(), width = width)?;
+            }
+            writeln!(out)?;
+            for (asset, values) in matrix.assets.iter().zip(&matrix.values) {
+                write!(out, "{:<width$}", asset.id(), width = width)?;
+                for value in values {
+                    let value = value.map(|v| format!("{:.2}", v)).unwrap_or_else(|| "-".to_string());
+                    write!(out, "  {:>width$}", value, width = width)?;
+                }
+                writeln!(out)?;
+            }
+        }
+        OutputFormat::Csv => export::write_matrix_csv(&mut out, &matrix)?,
+        OutputFormat::Json => export::write_matrix_json(&mut out, &matrix)?,
+    }
+    out.flush()?;
+    Ok(())
+}
+
+// Periodic contributions into BTC, the asset itself and cash, valued through time
+fn dca_command(options: &[String]) -> Result<()> {
+    let asset = asset_option(options)?;
+    let amount = match option(options, "--amount") {
+        Some(amount) => amount.parse().ok().filter(|amount: &f64| *amount > 0.0)
+            .ok_or_else(|| anyhow::anyhow!("Invalid --amount {}, expected a positive number", amount))?,
+        None => 100.0,
+    };
+    let currency = match option(options, "--currency") {
+        Some(code) => Currency::from_code(code).ok_or_else(|| anyhow::anyhow!("Unknown currency {}", code))?,
+        None => Currency::USD,
+    };
+    let frequency = match option(options, "--every") {
+        Some(name) => Frequency::parse(name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown --every {}, expected daily, weekly, monthly, quarterly or yearly", name))?,
+        None => Frequency::Monthly,
+    };
+    let plan = DcaPlan { amount, currency, frequency, from: date_option(options, "--from")?, to: date_option(options, "--to")? };
+    let alignment = alignment(options)?;
+    // Cash lines for the enabled currencies, which need the plan currency's rates too
+    let cash = currencies(options);
+    let mut currencies: Vec<Currency> = cash.iter().copied()
+        .chain(std::iter::once(currency))
+        .filter(|c| *c != Currency::USD)
+        .collect();
+    currencies.sort_by_key(|c| c.code);
+    currencies.dedup();
+    let data = load(options, &currencies, alignment, None);
+
+    let lines = dca::simulate(&data.prices, &data.fx, asset, &plan, &cash, alignment);
+    let Some(contributed) = lines.first().and_then(|line| line.last()).map(|last| last.contributed) else {
+        return Err(anyhow::anyhow!("Not enough data for {:?} in the requested dates", asset));
+    };
+    eprintln!("{:.2} {} {} into each line, {:.2} {} in total", amount, currency.code,
+              frequency.label().to_lowercase(), contributed, currency.code);
+    for line in &lines {
+        if let Some(last) = line.last() {
+            eprintln!("  {:<16} {:>14.6} {:<6} worth {:>14.2} {} ({:+.2}%), {:.4} {}", line.holding.label(),
+                      last.held, line.holding.unit(), last.value, currency.code,
+                      line.return_pct().unwrap_or(0.0), last.value_in_asset, asset.unit());
+        }
+    }
+    print_table(options, dca::table(&lines, asset))
+}
+
+// The saved holdings valued on every date since the first purchase
+fn portfolio_command(options: &[String]) -> Result<()> {
+    let path = option(options, "--portfolio").map(std::path::PathBuf::from).unwrap_or_else(Portfolio::default_path);
+    let holdings = Portfolio::load(&path)?;
+    if holdings.positions.is_empty() {
+        return Err(anyhow::anyhow!("No positions in {}, add them in the app's Portfolio view", path.display()));
+    }
+    let asset = asset_option(options)?;
+    let currency = match option(options, "--currency") {
+        Some(code) => Currency::from_code(code).ok_or_else(|| anyhow::anyhow!("Unknown currency {}", code))?,
+        None => Currency::USD,
+    };
+    let alignment = alignment(options)?;
+    // Exchange rates for the display currency and every cash holding
+    let mut currencies: Vec<Currency> = holdings.instruments().into_iter()
+        .filter_map(|instrument| match instrument {
+            portfolio::Instrument::Cash(cash) => Some(cash),
+            portfolio::Instrument::Asset(_) => None,
+        })
+        .chain(std::iter::once(currency))
+        .filter(|c| *c != Currency::USD)
+        .collect();
+    currencies.sort_by_key(|c| c.code);
+    currencies.dedup();
+    let data = load(options, &currencies, alignment, None);
+
+    let history = portfolio::value(&data.prices, &data.fx, &holdings, currency, asset, alignment);
+    let Some(last) = history.points.last() else {
+        return Err(anyhow::anyhow!("No prices for the holdings in {}", path.display()));
+    };
+    eprintln!("{} positions from {}, on {}:", holdings.positions.len(), path.display(), last.date);
+    for (i, instrument) in history.instruments.iter().enumerate() {
+        eprintln!("  {:<16} {:>14.6} {:<6} worth {:>14.2} {}", instrument.label(), last.held[i], instrument.unit(),
+                  last.parts_usd[i] * last.rate, currency.code);
+    }
+    eprintln!("  {:<16} {:>14.2} {} or {:.4} {} of {}", "Total", last.value(), currency.code,
+              last.value_in_asset(), asset.unit(), asset.base_name());
+    print_table(options, portfolio::table(&history, asset))
+}
+
+fn write_stats(out: &mut impl Write, stats: &[LineStats], frequency: Frequency) -> Result<()> {
+    let pct = |value: Option<f64>| value.map(|v| format!("{:+.2}%", v)).unwrap_or_else(|| "-".to_string());
+    let dated = |value: Option<f64>, date: Option<NaiveDate>| match date {
+        Some(date) => format!("{} ({})", pct(value), date),
+        None => "-".to_string(),
+    };
+    writeln!(out, "{:<6}  {:<23}  {:>12}  {:>10}  {:>10}  {:<41}  {:<24}  {:<24}  {:>6}", "line", "range", "total return", "CAGR",
+             "volatility", "max drawdown", format!("best {}", frequency.unit()), format!("worst {}", frequency.unit()), "sharpe")?;
+    for line in stats {
+        writeln!(out, "{:<6}  {:<23}  {:>12}  {:>10}  {:>10}  {:<41}  {:<24}  {:<24}  {:>6}",
+                 line.line,
+                 format!("{} {}", line.start, line.end),
+                 pct(Some(line.total_return_pct)),
+                 pct(line.cagr_pct),
+                 line.volatility_pct.map(|v| format!("{:.2}%", v)).unwrap_or_else(|| "-".to_string()),
+                 format!("{:.2}% ({} to {})", line.max_drawdown_pct, line.drawdown_peak, line.drawdown_trough),
+                 dated(line.best_period_pct, line.best_period),
+                 dated(line.worst_period_pct, line.worst_period),
+                 line.sharpe.map(|s| format!("{:.2}", s)).unwrap_or_else(|| "-".to_string()))?;
+    }
+    Ok(())
+}
+
+// JSON API for other tools, see `server` for the endpoints
+fn serve_command(options: &[String]) -> Result<()> {
+    let host = option(options, "--host").unwrap_or("127.0.0.1");
+    let port = option(options, "--port").unwrap_or("8080");
+    let addr: std::net::SocketAddr = format!("{}:{}", host, port).parse()
+        .with_context(|| format!("Invalid address {}:{}", host, port))?;
+    let cache = PriceCache::default_location().with_full_refresh(flag(options, "--full-refresh"));
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(crate::server::serve(addr, cache, currencies(options)))
+}
+
+// Refresh every asset and print a summary, e.g. from a cron job to keep the cache warm
+fn fetch_command(options: &[String]) -> Result<()> {
+    let currencies = currencies(options);
+    let alignment = alignment(options)?;
+    // Like the GUI refresh button: only days since the last cached date are requested
+    let data = load(options, &currencies, alignment, Some(chrono::Duration::zero()));
+
+    let assets = Asset::tracked().iter().copied().chain(Asset::baskets());
+    println!("{:<16} {:>7}  {:<10}  {:<10}  sources", "asset", "points", "first", "last");
+    for asset in assets {
+        match data.prices.get(&asset) {
+            Some(prices) if !prices.is_empty() => {
+                let sources: Vec<&str> = source_ranges(prices).iter()
+                    .map(|(source, _, _)| source.unwrap_or("derived"))
+                    .collect();
+                println!("{:<16} {:>7}  {}  {}  {}", asset.id(), prices.len(),
+                         prices[0].date, prices[prices.len() - 1].date, sources.join(", "));
+            }
+            _ => println!("{:<16} {:>7}", asset.id(), "missing"),
+        }
+    }
+    for currency in &currencies {
+        if !data.fx.has(*currency) {
+            println!("No USD/{} exchange rates", currency.code);
+        }
+    }
+    Ok(())
+}
+
+fn list_assets() {
+    for &asset in Asset::tracked() {
+        println!("{:<16} {:<20} {}", asset.id(), asset.category(), asset.name());
+    }
+    for basket in Asset::baskets() {
+        println!("{:<16} {:<20} {}", basket.id(), basket.category(), basket.name());
+    }
+}
@@ -0,0 +1,230 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use async_trait::async_trait;
+use anyhow::{Context, Result};
+use crate::models::Asset;
+use crate::models::registry::{CsvSeries, FillMethod};
+use crate::data::PriceData;
+use crate::data::source::{PriceSource, SourceCoverage};
+
+// Date columns used by FRED (DATE, observation_date) and Statistics Canada (REF_DATE) downloads
+const DATE_COLUMNS: [&str; 3] = ["DATE", "observation_date", "REF_DATE"];
+
+/// Series such as CPI read from CSV files or URLs, as configured per asset in `assets.json`
+pub struct CsvSource {
+    client: reqwest::Client,
+}
+
+impl CsvSource {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+        }
+    }
+
+    fn is_url(path: &str) -> bool {
+        path.starts_with("http://") || path.starts_with("https://")
+    }
+
+    async fn read(&self, path: &str) -> Result<String> {
+        if Self::is_url(path) {
+            let response = self.client.get(path)
+                .timeout(std::time::Duration::from_secs(30))
+                .send()
+                .await?;
+            if !response.status().is_success() {
+                return Err(anyhow::anyhow!("Failed to download {}: {}", path, response.status()));
+            }
+            Ok(response.text().await?)
+        } else {
+            tokio::fs::read_to_string(path).await.with_context(|| format!("Failed to read {}", path))
+        }
+    }
+}
+
+/// Parse the (date, value) observations of `series` from CSV text, sorted by date
+pub fn parse_series(text: &str, series: &CsvSeries) -> Result<Vec<(NaiveDate, f64)>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|h| h.trim().eq_ignore_ascii_case(name));
+
+    let date_idx = match &series.date_column {
+        Some(name) => column(name).ok_or_else(|| anyhow::anyhow!("No column {} in {}", name, series.path))?,
+        None => DATE_COLUMNS.iter().find_map(|name| column(name)).unwrap_or(0),
+    };
+    let value_idx = match &series.value_column {
+        Some(name) => column(name).ok_or_else(|| anyhow::anyhow!("No column {} in {}", name, series.path))?,
+        None => column("VALUE").unwrap_or(headers.len().saturating_sub(1)),
+    };
+    let filters = series.filter.iter()
+        .map(|(name, value)| {
+            column(name)
+                .map(|idx| (idx, value.as_str()))
+                .ok_or_else(|| anyhow::anyhow!("No column {} in {}", name, series.path))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut observations = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        if !filters.iter().all(|(idx, value)| record.get(*idx).map(str::trim) == Some(*value)) {
+            continue;
+        }
+        // Missing observations are "." in FRED and empty in StatCan files
+        let (Some(date), Some(value)) = (
+            record.get(date_idx).and_then(parse_date),
+            record.get(value_idx).and_then(|v| v.trim().parse::<f64>().ok()),
+        ) else {
+            continue;
+        };
+        observations.push((date, value));
+    }
+
+    observations.sort_by_key(|(date, _)| *date);
+    observations.dedup_by_key(|(date, _)| *date);
+    Ok(observations)
+}
+
+// Daily ("2024-01-31"), monthly ("2024-01") or yearly ("2024") dates
+fn parse_date(text: &str) -> Option<NaiveDate> {
+    let text = text.trim();
+    NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()
+        .or_else(|| NaiveDate::parse_from_str(&format!("{}-01", text), "%Y-%m-%d").ok())
+        .or_else(|| NaiveDate::parse_from_str(&format!("{}-01-01", text), "%Y-%m-%d").ok())
+}
+
+/// Expand observations to one point per day. Step fill holds the last observation for
+/// as long as the gap before it (one period for monthly data); linear fill stops at
+/// the last observation.
+pub fn fill_daily(observations: &[(NaiveDate, f64)], fill: FillMethod) -> Vec<(NaiveDate, f64)> {
+    let mut daily = Vec::new();
+    for pair in observations.windows(2) {
+        let ((from, from_value), (to, to_value)) = (pair[0], pair[1]);
+        let days = (to - from).num_days();
+        for offset in 0..days {
+            let value = match fill {
+                FillMethod::Step => from_value,
+                FillMethod::Linear => from_value + (to_value - from_value) * offset as f64 / days as f64,
+            };
+            daily.push((from + Duration::days(offset), value));
+        }
+    }
+
+    if let Some(&(last, value)) = observations.last() {
+        let period = match (fill, observations.len()) {
+            (FillMethod::Step, n) if n >= 2 => (last - observations[n - 2].0).num_days(),
+            _ => 1,
+        };
+        for offset in 0..period {
+            daily.push((last + Duration::days(offset), value));
+        }
+    }
+    daily
+}
+
+#[async_trait]
+impl PriceSource for CsvSource {
+    fn name(&self) -> &'static str {
+        "CSV"
+    }
+
+    fn coverage(&self, asset: Asset) -> Option<SourceCoverage> {
+        asset.definition()?.csv.as_ref()?;
+        Some(SourceCoverage { earliest: None, synthetic: false })
+    }
+
+    fn cacheable(&self, asset: Asset) -> bool {
+        // Downloads are cached like any other source, local files are always reread
+        asset.definition()
+            .and_then(|definition| definition.csv.as_ref())
+            .is_some_and(|series| Self::is_url(&series.path))
+    }
+
+    async fn fetch_asset(&self, asset: Asset, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<PriceData>> {
+        let series = asset.definition()
+            .and_then(|definition| definition.csv.as_ref())
+            .ok_or_else(|| anyhow::anyhow!("No CSV series configured for {:?}", asset))?;
+
+        let text = self.read(&series.path).await?;
+        let observations = parse_series(&text, series)?;
+        eprintln!("Read {} observations for {:?} from {}", observations.len(), asset, series.path);
+
+        let (start, end) = (start.date_naive(), end.date_naive());
+        Ok(fill_daily(&observations, series.fill)
+            .into_iter()
+            .filter(|(date, _)| *date >= start && *date <= end)
+            .map(|(date, value)| PriceData {
+                date,
+                price_usd: value,
+                source: None,
+                ohlcv: None,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::data::fixtures::date;
+
+    fn series(filter: &[(&str, &str)], fill: FillMethod) -> CsvSeries {
+        CsvSeries {
+            path: "test.csv".to_string(),
+            date_column: None,
+            value_column: None,
+            filter: filter.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<HashMap<_, _>>(),
+            fill,
+        }
+    }
+
+    #[test]
+    fn fred_missing_values_are_skipped() {
+        let text = "observation_date,CPIAUCSL\n2024-01-01,308.4\n2024-02-01,.\n2024-03-01,310.3\n";
+        let observations = parse_series(text, &series(&[], FillMethod::Step)).unwrap();
+        assert_eq!(observations, vec![(date(2024, 1, 1), 308.4), (date(2024, 3, 1), 310.3)]);
+    }
+
+    #[test]
+    fn statcan_rows_are_filtered() {
+        let text = "\
+REF_DATE,GEO,Products and product groups,VALUE
+2024-01,Canada,All-items,158.3
+2024-01,Ontario,All-items,159.0
+2024-01,Canada,Food,190.2
+2024-02,Canada,All-items,158.8
+2024-03,Canada,All-items,
+";
+        let filter = [("GEO", "Canada"), ("Products and product groups", "All-items")];
+        let observations = parse_series(text, &series(&filter, FillMethod::Step)).unwrap();
+        assert_eq!(observations, vec![(date(2024, 1, 1), 158.3), (date(2024, 2, 1), 158.8)]);
+    }
+
+    #[test]
+    fn unknown_filter_column_is_an_error() {
+        let text = "REF_DATE,VALUE\n2024-01,1.0\n";
+        assert!(parse_series(text, &series(&[("GEO", "Canada")], FillMethod::Step)).is_err());
+    }
+
+    #[test]
+    fn step_fill_holds_last_month_for_a_month() {
+        let observations = [(date(2024, 1, 1), 100.0), (date(2024, 2, 1), 131.0)];
+        let daily = fill_daily(&observations, FillMethod::Step);
+        // 31 days of January at the January value, then February held for as long
+        assert_eq!(daily.len(), 62);
+        assert!(daily[..31].iter().all(|(_, v)| *v == 100.0));
+        assert!(daily[31..].iter().all(|(_, v)| *v == 131.0));
+        assert_eq!(daily.last().unwrap().0, date(2024, 3, 2));
+    }
+
+    #[test]
+    fn linear_fill_interpolates_and_stops_at_last_observation() {
+        let observations = [(date(2024, 1, 1), 100.0), (date(2024, 2, 1), 131.0)];
+        let daily = fill_daily(&observations, FillMethod::Linear);
+        assert_eq!(daily.len(), 32);
+        assert_eq!(daily[10], (date(2024, 1, 11), 110.0));
+        assert_eq!(*daily.last().unwrap(), (date(2024, 2, 1), 131.0));
+    }
+}
@@ -0,0 +1,335 @@
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use crate::data::PriceData;
+
+/// Default distance a value may be from the date it is used for; a month bridges
+/// market holidays and monthly series without matching unrelated prices
+pub const DEFAULT_MAX_DAYS: i64 = 30;
+
+// The closest observation on one side of a date, if any
+type Neighbor = Option<(NaiveDate, f64)>;
+
+/// How a series gets a value on a date it has no observation for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlignPolicy {
+    /// Only observations on the date itself
+    Exact,
+    /// The last observation before the date
+    ForwardFill,
+    /// The first observation after the date
+    BackwardFill,
+    /// The closest observation either side, the earlier one on a tie
+    Nearest,
+    /// Straight line between the observations either side
+    Linear,
+}
+
+impl AlignPolicy {
+    pub fn all() -> [AlignPolicy; 5] {
+        [AlignPolicy::Exact, AlignPolicy::ForwardFill, AlignPolicy::BackwardFill, AlignPolicy::Nearest, AlignPolicy::Linear]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Exact => "Same day only",
+            Self::ForwardFill => "Last known value",
+            Self::BackwardFill => "Next known value",
+            Self::Nearest => "Nearest day",
+            Self::Linear => "Interpolate",
+        }
+    }
+
+    /// "exact", "ffill"/"forward", "bfill"/"backward", "nearest" or "linear"
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "exact" => Some(Self::Exact),
+            "ffill" | "forward" => Some(Self::ForwardFill),
+            "bfill" | "backward" => Some(Self::BackwardFill),
+            "nearest" => Some(Self::Nearest),
+            "linear" => Some(Self::Linear),
+            _ => None,
+        }
+    }
+}
+
+/// Which days a series that only trades on some days (futures, FX) is compared on
+/// against one that trades every day (Bitcoin)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Calendar {
+    /// Every day, with prices on non-trading days picked by the `AlignPolicy`
+    #[default]
+    AllDays,
+    /// Only days the series has its own price on
+    TradingDays,
+    /// Every day, carrying the last traded price over non-trading days (which are marked)
+    CarryForward,
+}
+
+impl Calendar {
+    pub fn all() -> [Calendar; 3] {
+        [Calendar::AllDays, Calendar::TradingDays, Calendar::CarryForward]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::AllDays => "Every day",
+            Self::TradingDays => "Trading days only",
+            Self::CarryForward => "Carry forward (marked)",
+        }
+    }
+
+    /// "all", "trading" or "carry"
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "all" => Some(Self::AllDays),
+            "trading" => Some(Self::TradingDays),
+            "carry" => Some(Self::CarryForward),
+            _ => None,
+        }
+    }
+}
+
+/// A policy plus how many days away an observation may be and still be used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alignment {
+    pub policy: AlignPolicy,
+    pub max_days: i64,
+    pub calendar: Calendar,
+}
+
+impl Default for Alignment {
+    /// Nearest observation within a month, as asset prices were always matched to Bitcoin dates
+    fn default() -> Self {
+        Self::new(AlignPolicy::Nearest, DEFAULT_MAX_DAYS)
+    }
+}
+
+impl Alignment {
+    pub fn new(policy: AlignPolicy, max_days: i64) -> Self {
+        Self { policy, max_days, calendar: Calendar::AllDays }
+    }
+
+    /// The policy actually applied; a calendar other than `AllDays` decides it
+    pub fn effective_policy(&self) -> AlignPolicy {
+        match self.calendar {
+            Calendar::AllDays => self.policy,
+            Calendar::TradingDays => AlignPolicy::Exact,
+            Calendar::CarryForward => AlignPolicy::ForwardFill,
+        }
+    }
+
+    // Value on `date` from the last observation on or before it and the first one after it
+    fn pick(&self, date: NaiveDate, before: Neighbor, after: Neighbor) -> Option<f64> {
+        let within = |(d, _): &(NaiveDate, f64)| (date - *d).num_days().abs() <= self.max_days;
+        let before = before.filter(within);
+        let after = after.filter(within);
+        if let Some((d, value)) = before {
+            if d == date {
+                return Some(value);
+            }
+        }
+        match self.effective_policy() {
+            AlignPolicy::Exact => None,
+            AlignPolicy::ForwardFill => before.map(|(_, v)| v),
+            AlignPolicy::BackwardFill => after.map(|(_, v)| v),
+            AlignPolicy::Nearest => match (before, after) {
+                (Some((b, _)), Some((a, value))) if (a - date) < (date - b) => Some(value),
+                (Some((_, value)), _) | (None, Some((_, value))) => Some(value),
+                (None, None) => None,
+            },
+            AlignPolicy::Linear => {
+                let ((b, b_value), (a, a_value)) = (before?, after?);
+                let t = (date - b).num_days() as f64 / (a - b).num_days() as f64;
+                Some(b_value + (a_value - b_value) * t)
+            }
+        }
+    }
+}
+
+/// A series sorted by date, for looking up values on dates it may not have
+#[derive(Debug, Clone, Default)]
+pub struct DateIndex {
+    points: Vec<(NaiveDate, f64)>,
+}
+
+impl DateIndex {
+    /// Index `points` in any order; a later point replaces an earlier one on the same date
+    pub fn new(points: impl IntoIterator<Item = (NaiveDate, f64)>) -> Self {
+        let sorted: BTreeMap<NaiveDate, f64> = points.into_iter().collect();
+        Self { points: sorted.into_iter().collect() }
+    }
+
+    pub fn from_prices(data: &[PriceData]) -> Self {
+        Self::new(data.iter().map(|p| (p.date, p.price_usd)))
+    }
+
+    /// Add `points`, replacing existing values on the same dates
+    pub fn extend(&mut self, points: impl IntoIterator<Item = (NaiveDate, f64)>) {
+        let existing = std::mem::take(&mut self.points);
+        *self = Self::new(existing.into_iter().chain(points));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn first_date(&self) -> Option<NaiveDate> {
+        self.points.first().map(|(date, _)| *date)
+    }
+
+    pub fn dates(&self) -> impl Iterator<Item = NaiveDate> + '_ {
+        self.points.iter().map(|(date, _)| *date)
+    }
+
+    pub fn points(&self) -> &[(NaiveDate, f64)] {
+        &self.points
+    }
+
+    // Neighbours of `date` given the index of the first point after it
+    fn around(&self, next: usize) -> (Neighbor, Neighbor) {
+        let before = next.checked_sub(1).map(|i| self.points[i]);
+        (before, self.points.get(next).copied())
+    }
+
+    /// Whether the series has its own value on `date`
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.points.binary_search_by_key(&date, |(d, _)| *d).is_ok()
+    }
+
+    /// Value on `date` under `alignment`, by binary search
+    pub fn get(&self, date: NaiveDate, alignment: Alignment) -> Option<f64> {
+        let next = self.points.partition_point(|(d, _)| *d <= date);
+        let (before, after) = self.around(next);
+        alignment.pick(date, before, after)
+    }
+
+    /// Value on each of `dates` in a single pass over the index. Dates should be in
+    /// ascending order; an earlier date than the last one costs a binary search.
+    pub fn join<'a>(&'a self, dates: impl IntoIterator<Item = NaiveDate> + 'a, alignment: Alignment) -> impl Iterator<Item = (NaiveDate, Option<f64>)> + 'a {
+        let mut next = 0;
+        let mut last: Option<NaiveDate> = None;
+        dates.into_iter().map(move |date| {
+            if last.is_some_and(|last| date < last) {
+                next = self.points.partition_point(|(d, _)| *d <= date);
+            }
+            while next < self.points.len() && self.points[next].0 <= date {
+                next += 1;
+            }
+            last = Some(date);
+            let (before, after) = self.around(next);
+            (date, alignment.pick(date, before, after))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::fixtures::day;
+
+    // 10 on Jan 1 and 50 on Jan 5
+    fn index() -> DateIndex {
+        DateIndex::new([(day(5), 50.0), (day(1), 10.0)])
+    }
+
+    fn get(policy: AlignPolicy, max_days: i64, d: u32) -> Option<f64> {
+        index().get(day(d), Alignment::new(policy, max_days))
+    }
+
+    #[test]
+    fn every_policy_uses_a_value_on_the_date() {
+        for policy in AlignPolicy::all() {
+            assert_eq!(get(policy, 30, 1), Some(10.0));
+            assert_eq!(get(policy, 30, 5), Some(50.0));
+        }
+    }
+
+    #[test]
+    fn exact_needs_a_value_on_the_date() {
+        assert_eq!(get(AlignPolicy::Exact, 30, 3), None);
+    }
+
+    #[test]
+    fn forward_fill_uses_the_last_value() {
+        assert_eq!(get(AlignPolicy::ForwardFill, 30, 4), Some(10.0));
+        assert_eq!(get(AlignPolicy::ForwardFill, 30, 9), Some(50.0));
+        assert_eq!(index().get(day(1).pred_opt().unwrap(), Alignment::new(AlignPolicy::ForwardFill, 30)), None);
+    }
+
+    #[test]
+    fn backward_fill_uses_the_next_value() {
+        assert_eq!(get(AlignPolicy::BackwardFill, 30, 2), Some(50.0));
+        assert_eq!(get(AlignPolicy::BackwardFill, 30, 6), None);
+    }
+
+    #[test]
+    fn nearest_prefers_the_earlier_value_on_a_tie() {
+        assert_eq!(get(AlignPolicy::Nearest, 30, 2), Some(10.0));
+        assert_eq!(get(AlignPolicy::Nearest, 30, 3), Some(10.0));
+        assert_eq!(get(AlignPolicy::Nearest, 30, 4), Some(50.0));
+    }
+
+    #[test]
+    fn linear_interpolates_between_values() {
+        assert_eq!(get(AlignPolicy::Linear, 30, 2), Some(20.0));
+        assert_eq!(get(AlignPolicy::Linear, 30, 4), Some(40.0));
+        // Nothing to interpolate towards after the last value
+        assert_eq!(get(AlignPolicy::Linear, 30, 6), None);
+    }
+
+    #[test]
+    fn max_days_limits_both_sides() {
+        // Jan 3 is two days from either value
+        assert_eq!(get(AlignPolicy::ForwardFill, 1, 3), None);
+        assert_eq!(get(AlignPolicy::ForwardFill, 2, 3), Some(10.0));
+        assert_eq!(get(AlignPolicy::BackwardFill, 1, 3), None);
+        assert_eq!(get(AlignPolicy::BackwardFill, 2, 3), Some(50.0));
+        assert_eq!(get(AlignPolicy::Nearest, 1, 3), None);
+        // Only the value within reach is used
+        assert_eq!(get(AlignPolicy::Nearest, 1, 4), Some(50.0));
+        assert_eq!(get(AlignPolicy::Linear, 2, 2), None);
+        assert_eq!(get(AlignPolicy::Linear, 3, 2), Some(20.0));
+        // Past the last value
+        assert_eq!(get(AlignPolicy::ForwardFill, 2, 7), Some(50.0));
+        assert_eq!(get(AlignPolicy::ForwardFill, 2, 8), None);
+    }
+
+    #[test]
+    fn join_matches_get_in_any_order() {
+        let index = index();
+        let dates: Vec<NaiveDate> = [1, 2, 3, 4, 5, 6, 3, 1, 9].into_iter().map(day).collect();
+        for policy in AlignPolicy::all() {
+            let alignment = Alignment::new(policy, 3);
+            let joined: Vec<(NaiveDate, Option<f64>)> = index.join(dates.iter().copied(), alignment).collect();
+            let expected: Vec<(NaiveDate, Option<f64>)> = dates.iter().map(|d| (*d, index.get(*d, alignment))).collect();
+            assert_eq!(joined, expected, "{:?}", policy);
+        }
+    }
+
+    // Friday Jan 5 and Monday Jan 8 2024, joined onto every day from Friday to Monday
+    fn weekend(calendar: Calendar, policy: AlignPolicy) -> Vec<Option<f64>> {
+        let index = DateIndex::new([(day(5), 100.0), (day(8), 130.0)]);
+        let alignment = Alignment { calendar, ..Alignment::new(policy, 30) };
+        index.join((5..=8).map(day), alignment).map(|(_, value)| value).collect()
+    }
+
+    #[test]
+    fn trading_days_leave_the_weekend_empty() {
+        for policy in AlignPolicy::all() {
+            assert_eq!(weekend(Calendar::TradingDays, policy), vec![Some(100.0), None, None, Some(130.0)]);
+        }
+    }
+
+    #[test]
+    fn carry_forward_holds_friday_over_the_weekend() {
+        for policy in AlignPolicy::all() {
+            assert_eq!(weekend(Calendar::CarryForward, policy), vec![Some(100.0), Some(100.0), Some(100.0), Some(130.0)]);
+        }
+    }
+
+    #[test]
+    fn all_days_use_the_policy() {
+        assert_eq!(weekend(Calendar::AllDays, AlignPolicy::BackwardFill), vec![Some(100.0), Some(130.0), Some(130.0), Some(130.0)]);
+        assert_eq!(weekend(Calendar::AllDays, AlignPolicy::Linear), vec![Some(100.0), Some(110.0), Some(120.0), Some(130.0)]);
+    }
+}
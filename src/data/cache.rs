@@ -0,0 +1,198 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use anyhow::Result;
+use crate::data::PriceData;
+
+// Bump when the on-disk format changes so old entries are refetched
+const CACHE_VERSION: u32 = 2;
+
+// By default, entries fetched more recently than this are used without hitting the network
+const FRESH_FOR_HOURS: i64 = 6;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub version: u32,
+    pub fetched_at: DateTime<Utc>,
+    /// Start of the range originally requested, so a wider request refetches everything
+    pub requested_start: DateTime<Utc>,
+    pub data: Vec<PriceData>,
+}
+
+impl CacheEntry {
+    fn is_fresh(&self, max_age: Duration) -> bool {
+        Utc::now() - self.fetched_at < max_age
+    }
+
+    fn covers(&self, start: DateTime<Utc>) -> bool {
+        self.version == CACHE_VERSION && self.requested_start <= start && !self.data.is_empty()
+    }
+}
+
+/// On-disk cache of daily price history, one JSON file per source and asset
+#[derive(Debug, Clone)]
+pub struct PriceCache {
+    dir: PathBuf,
+    full_refresh: bool,
+    max_age: Duration,
+}
+
+impl PriceCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            full_refresh: false,
+            max_age: Duration::hours(FRESH_FOR_HOURS),
+        }
+    }
+
+    /// `$PRICE_CACHE_DIR`, else `~/.cache/asset_price_watcher`, else `.price_cache`
+    pub fn default_location() -> Self {
+        let dir = std::env::var_os("PRICE_CACHE_DIR")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache").join("asset_price_watcher")))
+            .unwrap_or_else(|| PathBuf::from(".price_cache"));
+        Self::new(dir)
+    }
+
+    /// Ignore existing entries and refetch the full range, overwriting the cache
+    pub fn with_full_refresh(mut self, full_refresh: bool) -> Self {
+        self.full_refresh = full_refresh;
+        self
+    }
+
+    /// Entries older than `max_age` are topped up with the days since their last date;
+    /// zero always checks for new days
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// For reloads after the first: every entry is used (even after a `--full-refresh`
+    /// start) and topped up with the days since its last date
+    pub fn for_refresh(self) -> Self {
+        self.with_full_refresh(false).with_max_age(Duration::zero())
+    }
+
+    pub fn is_fresh(&self, entry: &CacheEntry) -> bool {
+        entry.is_fresh(self.max_age)
+    }
+
+    fn path_for(&self, key: &str) -> PathBuf {
+        let file_name: String = key.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c.to_ascii_lowercase() } else { '_' })
+            .collect();
+        self.dir.join(format!("{}.json", file_name))
+    }
+
+    /// Cached entry for `key` if it is usable for a request starting at `start`
+    pub fn load(&self, key: &str, start: DateTime<Utc>) -> Option<CacheEntry> {
+        if self.full_refresh {
+            return None;
+        }
+        let contents = fs::read_to_string(self.path_for(key)).ok()?;
+        match serde_json::from_str::<CacheEntry>(&contents) {
+            Ok(entry) if entry.covers(start) => Some(entry),
+            Ok(_) => None,
+            Err(e) => {
+                eprintln!("Ignoring unreadable cache entry for {}: {}", key, e);
+                None
+            }
+        }
+    }
+
+    pub fn store(&self, key: &str, requested_start: DateTime<Utc>, data: &[PriceData]) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let entry = CacheEntry {
+            version: CACHE_VERSION,
+            fetched_at: Utc::now(),
+            requested_start,
+            data: data.to_vec(),
+        };
+        fs::write(self.path_for(key), serde_json::to_string(&entry)?)?;
+        Ok(())
+    }
+
+    /// Remove every cached entry
+    pub fn clear(&self) -> Result<()> {
+        if self.dir.exists() {
+            for entry in fs::read_dir(&self.dir)? {
+                let path = entry?.path();
+                if path.extension().is_some_and(|ext| ext == "json") {
+                    fs::remove_file(path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use crate::data::fixtures::{daily, temp_cache};
+
+    fn points() -> Vec<PriceData> {
+        daily(&[1.0, 2.0, 3.0, 4.0, 5.0])
+    }
+
+    #[test]
+    fn load_returns_entry_covering_the_request() {
+        let cache = temp_cache("cache_covers");
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        cache.store("gold", start, &points()).unwrap();
+
+        let entry = cache.load("gold", start + Duration::days(2)).unwrap();
+        assert_eq!(entry.data.len(), 5);
+        assert!(cache.is_fresh(&entry));
+        // A request starting before the cached range needs the missing days
+        assert!(cache.load("gold", start - Duration::days(1)).is_none());
+    }
+
+    #[test]
+    fn stale_entry_is_loaded_but_not_fresh() {
+        let cache = temp_cache("cache_stale").with_max_age(Duration::zero());
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        cache.store("gold", start, &points()).unwrap();
+
+        let entry = cache.load("gold", start).unwrap();
+        assert!(!cache.is_fresh(&entry));
+    }
+
+    #[test]
+    fn full_refresh_ignores_entries() {
+        let cache = temp_cache("cache_full_refresh");
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        cache.store("gold", start, &points()).unwrap();
+
+        assert!(cache.clone().with_full_refresh(true).load("gold", start).is_none());
+        assert!(cache.load("gold", start).is_some());
+    }
+
+    #[test]
+    fn refresh_after_full_refresh_tops_up_entries() {
+        let cache = temp_cache("cache_refresh").with_full_refresh(true);
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        cache.store("gold", start, &points()).unwrap();
+        assert!(cache.load("gold", start).is_none());
+
+        let refresh = cache.for_refresh();
+        let entry = refresh.load("gold", start).unwrap();
+        assert_eq!(entry.data.len(), 5);
+        // Stale, so only the days after the last cached date are fetched
+        assert!(!refresh.is_fresh(&entry));
+    }
+
+    #[test]
+    fn old_version_is_ignored() {
+        let cache = temp_cache("cache_version");
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let entry = CacheEntry { version: CACHE_VERSION - 1, fetched_at: Utc::now(), requested_start: start, data: points() };
+        fs::create_dir_all(&cache.dir).unwrap();
+        fs::write(cache.path_for("gold"), serde_json::to_string(&entry).unwrap()).unwrap();
+
+        assert!(cache.load("gold", start).is_none());
+    }
+}
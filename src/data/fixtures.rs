@@ -0,0 +1,36 @@
+use chrono::NaiveDate;
+use crate::data::PriceData;
+use crate::data::cache::PriceCache;
+
+pub fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+/// January `d`, 2024
+pub fn day(d: u32) -> NaiveDate {
+    date(2024, 1, d)
+}
+
+/// A close without a bar or source
+pub fn point(date: NaiveDate, price_usd: f64) -> PriceData {
+    PriceData { date, price_usd, source: None, ohlcv: None }
+}
+
+/// One point per date, the i-th priced at `price(i)`
+pub fn prices(dates: impl IntoIterator<Item = NaiveDate>, price: impl Fn(f64) -> f64) -> Vec<PriceData> {
+    dates.into_iter().enumerate()
+        .map(|(i, date)| point(date, price(i as f64)))
+        .collect()
+}
+
+/// `values` on consecutive days from January 1, 2024
+pub fn daily(values: &[f64]) -> Vec<PriceData> {
+    prices((1..=values.len() as u32).map(day), |i| values[i as usize])
+}
+
+/// An empty cache in a temporary directory unique to `name` and this test run
+pub fn temp_cache(name: &str) -> PriceCache {
+    let dir = std::env::temp_dir().join(format!("asset_price_watcher_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    PriceCache::new(dir)
+}
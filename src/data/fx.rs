@@ -0,0 +1,68 @@
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use crate::models::Currency;
+use crate::data::PriceData;
+use crate::data::align::{AlignPolicy, Alignment, Calendar, DateIndex};
+use crate::data::resample::{self, Aggregation, Frequency};
+
+// A rate older than this is too stale to carry forward (long FX market closures are rare)
+const MAX_CARRY_FORWARD_DAYS: i64 = 7;
+
+// Carry the last rate forward over weekends and holidays
+const RATE_ALIGNMENT: Alignment = Alignment {
+    policy: AlignPolicy::ForwardFill,
+    max_days: MAX_CARRY_FORWARD_DAYS,
+    calendar: Calendar::AllDays,
+};
+
+/// Daily USD -> currency exchange rates for every enabled currency
+#[derive(Debug, Clone, Default)]
+pub struct ExchangeRates {
+    rates: HashMap<&'static str, DateIndex>,
+}
+
+impl ExchangeRates {
+    /// Store a series of rates fetched from a `PriceSource` (rate in `price_usd`)
+    pub fn insert(&mut self, currency: Currency, series: &[PriceData]) {
+        self.rates.entry(currency.code)
+            .or_default()
+            .extend(series.iter().map(|point| (point.date, point.price_usd)));
+    }
+
+    pub fn has(&self, currency: Currency) -> bool {
+        currency == Currency::USD || self.rates.get(currency.code).is_some_and(|r| !r.is_empty())
+    }
+
+    /// Units of `currency` per USD on `date`, carrying the last rate forward over
+    /// weekends and holidays
+    pub fn rate(&self, currency: Currency, date: NaiveDate) -> Option<f64> {
+        if currency == Currency::USD {
+            return Some(1.0);
+        }
+        self.rates.get(currency.code)?.get(date, RATE_ALIGNMENT)
+    }
+
+    /// `rate` on each of `dates` (in ascending order) in a single pass
+    pub fn rates(&self, currency: Currency, dates: impl IntoIterator<Item = NaiveDate>) -> Vec<Option<f64>> {
+        let dates = dates.into_iter();
+        if currency == Currency::USD {
+            return dates.map(|_| Some(1.0)).collect();
+        }
+        match self.rates.get(currency.code) {
+            Some(index) => index.join(dates, RATE_ALIGNMENT).map(|(_, rate)| rate).collect(),
+            None => dates.map(|_| None).collect(),
+        }
+    }
+
+    /// Rates resampled like prices, so a resampled price converts at its period's rate
+    pub fn resampled(&self, frequency: Frequency, aggregation: Aggregation) -> Self {
+        let rates = self.rates.iter()
+            .map(|(code, index)| (*code, DateIndex::new(resample::resample_values(index.points(), frequency, aggregation))))
+            .collect();
+        Self { rates }
+    }
+
+    pub fn convert(&self, price_usd: f64, currency: Currency, date: NaiveDate) -> Option<f64> {
+        self.rate(currency, date).map(|rate| price_usd * rate)
+    }
+}
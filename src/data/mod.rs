@@ -1,76 +1,543 @@
-use chrono::{NaiveDate, Utc, TimeZone};
-use std::collections::HashMap;
-use crate::models::Asset;
-use crate::yahoo_data::{YahooDataFetcher};
-use crate::alternative_data::{AlternativeDataFetcher};
+pub mod source;
+pub mod cache;
+pub mod fx;
+pub mod align;
+pub mod resample;
+// Price series and caches shared by the unit tests
+#[cfg(test)]
+pub mod fixtures;
 
-#[derive(Debug, Clone)]
+use chrono::{DateTime, NaiveDate, Utc, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use crate::models::{Asset, Currency};
+use crate::models::registry::AssetRegistry;
+use crate::models::basket::{Basket, WEIGHT_TOTAL};
+use self::source::SourceChain;
+use self::cache::PriceCache;
+use self::fx::ExchangeRates;
+use self::align::{AlignPolicy, Alignment, Calendar, DateIndex};
+use self::resample::{Aggregation, Frequency};
+
+// What a consumer basket costs in USD on its base date
+pub const BASKET_BASE_VALUE: f64 = 10_000.0;
+// Share of the basket weight that must have a price on a date for it to be plotted
+const MIN_BASKET_COVERAGE: f64 = 0.5;
+
+/// Daily open/high/low/close bar in USD, with volume where the source reports it
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Ohlcv {
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: Option<f64>,
+    pub adj_close: Option<f64>,
+}
+
+impl Ohlcv {
+    /// Scale all prices (not volume), e.g. 0.01 for futures quoted in cents
+    pub fn scaled(self, factor: f64) -> Self {
+        Self {
+            open: self.open * factor,
+            high: self.high * factor,
+            low: self.low * factor,
+            close: self.close * factor,
+            volume: self.volume,
+            adj_close: self.adj_close.map(|p| p * factor),
+        }
+    }
+    
+    /// Largest of high-low and the gaps from the previous close
+    pub fn true_range(&self, prev_close: Option<f64>) -> f64 {
+        let range = self.high - self.low;
+        match prev_close {
+            Some(prev) => range.max((self.high - prev).abs()).max((self.low - prev).abs()),
+            None => range,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PriceData {
     pub date: NaiveDate,
-    pub price_usd: f64,
-    pub price_cad: Option<f64>, // Will be calculated from USD * USD/CAD rate
-}
-
-// Get data from Yahoo Finance by default, fall back to alternative sources
-pub fn get_historical_data() -> HashMap<Asset, Vec<PriceData>> {
-    println!("Fetching data from Yahoo Finance...");
-    match fetch_yahoo_data() {
-        Ok(data) => {
-            if data.is_empty() {
-                println!("Yahoo Finance returned no data, trying alternative sources...");
-                fetch_alternative_data()
-            } else {
-                println!("Successfully fetched Yahoo Finance data");
-                data
-            }
+    pub price_usd: f64, // Close price, converted to other currencies with `ExchangeRates`
+    #[serde(skip)]
+    pub source: Option<&'static str>, // Name of the PriceSource this point came from
+    #[serde(default)]
+    pub ohlcv: Option<Ohlcv>, // Full bar when the source provides one
+}
+
+/// Everything a data load produces: USD prices per asset plus exchange rates
+#[derive(Debug, Clone, Default)]
+pub struct MarketData {
+    pub prices: HashMap<Asset, Vec<PriceData>>,
+    pub fx: ExchangeRates,
+}
+
+impl MarketData {
+    /// Recompute every configured basket with components matched by `alignment`
+    pub fn rebuild_baskets(&mut self, alignment: Alignment) {
+        for basket in AssetRegistry::global().baskets() {
+            match create_consumer_basket(basket, &self.prices, alignment) {
+                Some(data) => self.prices.insert(basket.asset, data),
+                None => self.prices.remove(&basket.asset),
+            };
         }
-        Err(e) => {
-            eprintln!("Failed to fetch Yahoo Finance data: {}", e);
-            eprintln!("Trying alternative data sources...");
-            fetch_alternative_data()
+    }
+
+    /// Prices and exchange rates resampled to `frequency`
+    pub fn resampled(self, frequency: Frequency, aggregation: Aggregation) -> MarketData {
+        if frequency == Frequency::Daily {
+            return self;
+        }
+        MarketData {
+            prices: resample::resample_all(&self.prices, frequency, aggregation),
+            fx: self.fx.resampled(frequency, aggregation),
         }
     }
 }
 
-fn fetch_yahoo_data() -> anyhow::Result<HashMap<Asset, Vec<PriceData>>> {
-    use tokio::runtime::Runtime;
-    
-    let rt = Runtime::new()?;
-    rt.block_on(async {
-        let fetcher = YahooDataFetcher::new();
-        let start = Utc.with_ymd_and_hms(1999, 1, 1, 0, 0, 0).unwrap(); // Extended to 1999 for max commodity history
-        let end = Utc::now();
-        
-        fetcher.fetch_historical_data(start, end).await
-    })
+/// Progress of a data load, reported per asset as it happens
+#[derive(Debug, Clone)]
+pub enum LoadProgress {
+    Fetching { asset: Asset, source: &'static str },
+    /// The previous source failed or had no data, trying the next one
+    Retrying { asset: Asset, source: &'static str },
+    Loaded { asset: Asset, data: Vec<PriceData> },
+    ExchangeRates { currency: Currency, rates: Vec<PriceData> },
+    Failed { asset: Asset, error: String },
+    Finished,
 }
 
-fn fetch_alternative_data() -> HashMap<Asset, Vec<PriceData>> {
+// Get data from the default source chain (Yahoo Finance first, then alternative sources),
+// calling `progress` as each asset is fetched
+pub fn get_historical_data(cache: Option<&PriceCache>, currencies: &[Currency], progress: &(dyn Fn(LoadProgress) + Send + Sync)) -> MarketData {
     use tokio::runtime::Runtime;
     
     match Runtime::new() {
         Ok(rt) => {
-            rt.block_on(async {
-                let fetcher = AlternativeDataFetcher::new();
-                let start = Utc.with_ymd_and_hms(1999, 1, 1, 0, 0, 0).unwrap(); // Extended to 1999 for max commodity history
-                let end = Utc::now();
-                
-                match fetcher.fetch_historical_data(start, end).await {
-                    Ok(data) => {
-                        println!("Successfully fetched alternative data");
-                        data
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to fetch alternative data: {}", e);
-                        HashMap::new()
-                    }
-                }
-            })
+            let start = Utc.with_ymd_and_hms(1999, 1, 1, 0, 0, 0).unwrap(); // Extended to 1999 for max commodity history
+            let end = Utc::now();
+            rt.block_on(fetch_from_chain(&SourceChain::default(), cache, currencies, start, end, progress))
         }
         Err(e) => {
-            eprintln!("Failed to create runtime for alternative data: {}", e);
-            HashMap::new()
+            eprintln!("Failed to create runtime for data fetching: {}", e);
+            progress(LoadProgress::Finished);
+            MarketData::default()
+        }
+    }
+}
+
+/// Fetch every tracked asset from the first source in `chain` that returns data for it,
+/// plus exchange rates for `currencies`, only requesting days after the last cached date
+/// when `cache` is given
+pub async fn fetch_from_chain(chain: &SourceChain, cache: Option<&PriceCache>, currencies: &[Currency], start: DateTime<Utc>, end: DateTime<Utc>, progress: &(dyn Fn(LoadProgress) + Send + Sync)) -> MarketData {
+    let mut all_data = HashMap::new();
+    let mut fx = ExchangeRates::default();
+    
+    // Assets quoted in another currency (e.g. a national CPI) need its rates to convert to USD
+    let mut rate_currencies = currencies.to_vec();
+    for currency in Asset::tracked().iter().map(|asset| asset.quote_currency()) {
+        if !rate_currencies.contains(&currency) {
+            rate_currencies.push(currency);
+        }
+    }
+    
+    // Fetch exchange rates first so each asset can be shown in every currency as it arrives
+    for &currency in rate_currencies.iter().filter(|c| **c != Currency::USD) {
+        let rates = fetch_exchange_rates(chain, cache, currency.code, start, end).await;
+        if rates.is_empty() {
+            eprintln!("No USD/{} rates available, {} prices will not be shown", currency.code, currency.code);
+            continue;
         }
+        fx.insert(currency, &rates);
+        progress(LoadProgress::ExchangeRates { currency, rates });
     }
+    
+    for &asset in Asset::tracked() {
+        let mut last_error = None;
+        
+        for source in chain.sources_for(asset) {
+            let coverage = match source.coverage(asset) {
+                Some(coverage) if !coverage.synthetic => coverage,
+                _ => continue,
+            };
+            if coverage.earliest.is_some_and(|earliest| earliest > end.date_naive()) {
+                continue;
+            }
+            
+            eprintln!("Fetching data for {:?} from {}", asset, source.name());
+            progress(match last_error {
+                Some(_) => LoadProgress::Retrying { asset, source: source.name() },
+                None => LoadProgress::Fetching { asset, source: source.name() },
+            });
+            let key = format!("{}_{}", source.name(), asset.id());
+            let source_cache = cache.filter(|_| source.cacheable(asset));
+            let fetched = fetch_cached(source_cache, &key, start, end, |from| source.fetch_asset(asset, from, end)).await
+                .map(|data| quoted_in_usd(asset, data, &fx));
+            match fetched {
+                Ok(data) if !data.is_empty() => {
+                    eprintln!("Successfully fetched {} data points", data.len());
+                    let data = with_source(data, source.name());
+                    let data = backfill_history(chain, cache, &fx, asset, source.name(), data, start).await;
+                    progress(LoadProgress::Loaded { asset, data: data.clone() });
+                    all_data.insert(asset, data);
+                    last_error = None;
+                    break;
+                }
+                Ok(_) => {
+                    eprintln!("{} returned no data for {:?}", source.name(), asset);
+                    last_error = Some(format!("{} returned no data", source.name()));
+                }
+                Err(e) => {
+                    eprintln!("Failed to fetch data for {:?} from {}: {}", asset, source.name(), e);
+                    last_error = Some(format!("{}: {}", source.name(), e));
+                }
+            }
+        }
+        
+        if let Some(error) = last_error {
+            progress(LoadProgress::Failed { asset, error });
+        }
+    }
+    
+    // Sample data is only used when none of the assets it covers could be fetched,
+    // so generated prices are never mixed with real ones
+    for source in chain.sources() {
+        let synthetic_assets: Vec<Asset> = Asset::tracked().iter()
+            .copied()
+            .filter(|a| source.coverage(*a).is_some_and(|c| c.synthetic))
+            .collect();
+        
+        if synthetic_assets.is_empty() || synthetic_assets.iter().any(|a| all_data.contains_key(a)) {
+            continue;
+        }
+        
+        eprintln!("No real data for {} assets, using sample data from {}", synthetic_assets.len(), source.name());
+        for asset in synthetic_assets {
+            match source.fetch_asset(asset, start, end).await {
+                Ok(data) if !data.is_empty() => {
+                    let data = with_source(data, source.name());
+                    progress(LoadProgress::Loaded { asset, data: data.clone() });
+                    all_data.insert(asset, data);
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Failed to generate data for {:?} from {}: {}", asset, source.name(), e),
+            }
+        }
+    }
+    
+    // Create each consumer basket as weighted average of other assets
+    for basket in AssetRegistry::global().baskets() {
+        if let Some(basket_data) = create_consumer_basket(basket, &all_data, Alignment::default()) {
+            progress(LoadProgress::Loaded { asset: basket.asset, data: basket_data.clone() });
+            all_data.insert(basket.asset, basket_data);
+        }
+    }
+    
+    eprintln!("Finished fetching data. Got data for {} assets.", all_data.len());
+    progress(LoadProgress::Finished);
+    MarketData { prices: all_data, fx }
+}
+
+fn with_source(mut data: Vec<PriceData>, source: &'static str) -> Vec<PriceData> {
+    for price in data.iter_mut() {
+        price.source = Some(source);
+    }
+    data
 }
 
+/// Extend `data` backwards with lower-priority sources that reach further into the past,
+/// e.g. CoinDesk Bitcoin prices from 2010 before Yahoo's Sept 2014 start
+async fn backfill_history(chain: &SourceChain, cache: Option<&PriceCache>, fx: &ExchangeRates, asset: Asset, primary: &str, mut data: Vec<PriceData>, start: DateTime<Utc>) -> Vec<PriceData> {
+    for source in chain.sources_for(asset) {
+        let Some(first_date) = data.first().map(|p| p.date) else {
+            break;
+        };
+        if source.name() == primary || first_date <= start.date_naive() {
+            continue;
+        }
+        let reaches_further = source.coverage(asset)
+            .is_some_and(|c| !c.synthetic && c.earliest.is_some_and(|earliest| earliest < first_date));
+        if !reaches_further {
+            continue;
+        }
+        
+        let gap_end = (first_date - chrono::Duration::days(1)).and_hms_opt(0, 0, 0).unwrap().and_utc();
+        eprintln!("Extending {:?} history before {} with {}", asset, first_date, source.name());
+        let key = format!("{}_{}", source.name(), asset.id());
+        let source_cache = cache.filter(|_| source.cacheable(asset));
+        match fetch_cached(source_cache, &key, start, gap_end, |from| source.fetch_asset(asset, from, gap_end)).await {
+            Ok(older) => {
+                let older = quoted_in_usd(asset, older, fx);
+                eprintln!("Added {} earlier data points from {}", older.len(), source.name());
+                data = merge_price_data(data, with_source(older, source.name()));
+            }
+            Err(e) => eprintln!("Failed to extend {:?} history from {}: {}", asset, source.name(), e),
+        }
+    }
+    data
+}
+
+/// Merge two series, preferring `preferred` on dates both contain
+pub fn merge_price_data(preferred: Vec<PriceData>, fallback: Vec<PriceData>) -> Vec<PriceData> {
+    let mut date_map: HashMap<NaiveDate, PriceData> = HashMap::new();
+    
+    for price in fallback {
+        date_map.insert(price.date, price);
+    }
+    for price in preferred {
+        date_map.insert(price.date, price);
+    }
+    
+    let mut merged: Vec<PriceData> = date_map.into_values().collect();
+    merged.sort_by_key(|p| p.date);
+    merged
+}
+
+/// Contiguous date ranges served by each source, in date order
+pub fn source_ranges(data: &[PriceData]) -> Vec<(Option<&'static str>, NaiveDate, NaiveDate)> {
+    let mut ranges: Vec<(Option<&'static str>, NaiveDate, NaiveDate)> = Vec::new();
+    for price in data {
+        match ranges.last_mut() {
+            Some((source, _, end)) if *source == price.source => *end = price.date,
+            _ => ranges.push((price.source, price.date, price.date)),
+        }
+    }
+    ranges
+}
+
+async fn fetch_exchange_rates(chain: &SourceChain, cache: Option<&PriceCache>, currency: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<PriceData> {
+    for source in chain.sources() {
+        let key = format!("{}_USD{}", source.name(), currency);
+        match fetch_cached(cache, &key, start, end, |from| source.fetch_exchange_rate(currency, from, end)).await {
+            Ok(rates) if !rates.is_empty() => {
+                eprintln!("Successfully fetched {} USD/{} rates from {}", rates.len(), currency, source.name());
+                return rates;
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Failed to fetch USD/{} rates: {}", currency, e),
+        }
+    }
+    Vec::new()
+}
+
+/// Fetch through the cache: fresh entries are returned as-is, otherwise only the days
+/// from the last cached date onwards are requested and merged into the entry
+async fn fetch_cached<F, Fut>(cache: Option<&PriceCache>, key: &str, start: DateTime<Utc>, end: DateTime<Utc>, fetch: F) -> anyhow::Result<Vec<PriceData>>
+where
+    F: Fn(DateTime<Utc>) -> Fut,
+    Fut: Future<Output = anyhow::Result<Vec<PriceData>>>,
+{
+    let Some(cache) = cache else {
+        return fetch(start).await;
+    };
+    
+    let cached = cache.load(key, start);
+    let in_range = |data: Vec<PriceData>| -> Vec<PriceData> {
+        data.into_iter()
+            .filter(|p| p.date >= start.date_naive() && p.date <= end.date_naive())
+            .collect()
+    };
+    
+    let (mut data, requested_start) = match cached {
+        Some(entry) if cache.is_fresh(&entry) => {
+            eprintln!("Using cached data for {} ({} points)", key, entry.data.len());
+            return Ok(in_range(entry.data));
+        }
+        Some(entry) => (entry.data, entry.requested_start),
+        None => (Vec::new(), start),
+    };
+    
+    // Refetch the last cached day too since it may have been a partial day
+    let fetch_from = match data.last() {
+        Some(last) => last.date.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+        None => start,
+    };
+    
+    match fetch(fetch_from).await {
+        Ok(fresh) => {
+            if !data.is_empty() {
+                eprintln!("Fetched {} new points for {} since {}", fresh.len(), key, fetch_from.date_naive());
+            }
+            if let Some(first_new) = fresh.first().map(|p| p.date) {
+                data.retain(|p| p.date < first_new);
+            }
+            data.extend(fresh);
+            
+            if !data.is_empty() {
+                if let Err(e) = cache.store(key, requested_start, &data) {
+                    eprintln!("Failed to write cache entry for {}: {}", key, e);
+                }
+            }
+            Ok(in_range(data))
+        }
+        Err(e) if !data.is_empty() => {
+            eprintln!("Failed to refresh {}, using stale cached data: {}", key, e);
+            Ok(in_range(data))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+// Convert prices of an asset quoted in another currency to USD, dropping days without a rate
+fn quoted_in_usd(asset: Asset, data: Vec<PriceData>, fx: &ExchangeRates) -> Vec<PriceData> {
+    let currency = asset.quote_currency();
+    if currency == Currency::USD {
+        return data;
+    }
+    let rates = fx.rates(currency, data.iter().map(|point| point.date));
+    data.into_iter()
+        .zip(rates)
+        .filter_map(|(mut point, rate)| {
+            point.price_usd /= rate.filter(|rate| *rate > 0.0)?;
+            Some(point)
+        })
+        .collect()
+}
+
+/// Value of the basket in USD over time: each component is normalized to its price
+/// on the basket's base date, so the basket costs `BASKET_BASE_VALUE` on that date.
+/// Components without a price on a date get one through `alignment`'s policy; its
+/// calendar only applies when the basket is compared with Bitcoin.
+pub fn create_consumer_basket(basket: &Basket, all_data: &HashMap<Asset, Vec<PriceData>>, alignment: Alignment) -> Option<Vec<PriceData>> {
+    let components: Vec<(f64, DateIndex)> = basket.components.iter()
+        .filter_map(|(asset, weight)| Some((*weight, DateIndex::from_prices(all_data.get(asset)?))))
+        .filter(|(_, prices)| !prices.is_empty())
+        .collect();
+    
+    let base_date = basket.base_date.or_else(|| {
+        components.iter().filter_map(|(_, prices)| prices.first_date()).max()
+    })?;
+    
+    // Price at the base date or the last one up to `max_days` before it (a base date on a
+    // weekend or holiday), else the first price after it for assets with a shorter history
+    let components: Vec<(f64, &DateIndex, f64)> = components.iter()
+        .filter_map(|(weight, prices)| {
+            let base = prices.get(base_date, Alignment::new(AlignPolicy::ForwardFill, alignment.max_days))
+                .or_else(|| prices.get(base_date, Alignment::new(AlignPolicy::BackwardFill, i64::MAX)))?;
+            (base > 0.0).then_some((*weight, prices, base))
+        })
+        .collect();
+    
+    let dates: Vec<NaiveDate> = components.iter()
+        .flat_map(|(_, prices, _)| prices.dates())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    
+    let alignment = Alignment { calendar: Calendar::AllDays, ..alignment };
+    let mut weighted_sums = vec![0.0; dates.len()];
+    let mut total_weights = vec![0.0; dates.len()];
+    for (weight, prices, base) in &components {
+        for (i, (_, price)) in prices.join(dates.iter().copied(), alignment).enumerate() {
+            if let Some(price) = price {
+                weighted_sums[i] += price / base * weight;
+                total_weights[i] += weight;
+            }
+        }
+    }
+    
+    // Only add data points where enough of the basket has a price
+    let basket_data: Vec<PriceData> = dates.into_iter()
+        .zip(weighted_sums.into_iter().zip(total_weights))
+        .filter(|(_, (_, total_weight))| *total_weight >= MIN_BASKET_COVERAGE * WEIGHT_TOTAL)
+        .map(|(date, (weighted_sum, total_weight))| PriceData {
+            date,
+            price_usd: weighted_sum / total_weight * BASKET_BASE_VALUE,
+            source: None,
+            ohlcv: None,
+        })
+        .collect();
+    
+    if basket_data.is_empty() {
+        eprintln!("Warning: No data could be calculated for the {} basket", basket.name);
+        None
+    } else {
+        eprintln!("Created {} basket with {} data points (base date {})", basket.name, basket_data.len(), base_date);
+        Some(basket_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Datelike;
+    use std::cell::RefCell;
+    use crate::data::fixtures::{self, day, temp_cache};
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap()
+    }
+
+    // Daily prices from `from` to `to`, priced at `price`
+    fn prices(from: u32, to: u32, price: f64) -> Vec<PriceData> {
+        fixtures::prices((from..=to).map(day), |_| price)
+    }
+
+    // Runs `fetch_cached` with a source returning days `from..=10` at `price`, recording
+    // the start of each request
+    fn fetch(cache: &PriceCache, start: DateTime<Utc>, price: f64, calls: &RefCell<Vec<DateTime<Utc>>>) -> Vec<PriceData> {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime.block_on(fetch_cached(Some(cache), "gold", start, at(10), |from| {
+            calls.borrow_mut().push(from);
+            let data = prices(from.date_naive().day(), 10, price);
+            async move { Ok(data) }
+        })).unwrap()
+    }
+
+    #[test]
+    fn fresh_entry_is_used_without_fetching() {
+        let cache = temp_cache("fetch_fresh");
+        cache.store("gold", at(1), &prices(1, 10, 1.0)).unwrap();
+        let calls = RefCell::new(Vec::new());
+
+        let data = fetch(&cache, at(1), 2.0, &calls);
+        assert!(calls.borrow().is_empty());
+        assert_eq!(data.len(), 10);
+        assert!(data.iter().all(|p| p.price_usd == 1.0));
+    }
+
+    #[test]
+    fn stale_entry_fetches_from_last_cached_day() {
+        let cache = temp_cache("fetch_stale");
+        cache.store("gold", at(1), &prices(1, 5, 1.0)).unwrap();
+        let stale = cache.clone().with_max_age(chrono::Duration::zero());
+        let calls = RefCell::new(Vec::new());
+
+        let data = fetch(&stale, at(1), 2.0, &calls);
+        // The last cached day is refetched in case it was partial
+        assert_eq!(*calls.borrow(), vec![at(5)]);
+        assert_eq!(data.len(), 10);
+        assert!(data[..4].iter().all(|p| p.price_usd == 1.0));
+        assert!(data[4..].iter().all(|p| p.price_usd == 2.0));
+        // The merged series is written back
+        assert_eq!(cache.load("gold", at(1)).unwrap().data.len(), 10);
+    }
+
+    #[test]
+    fn entry_missing_start_of_range_is_refetched() {
+        let cache = temp_cache("fetch_range");
+        cache.store("gold", at(3), &prices(3, 10, 1.0)).unwrap();
+        let calls = RefCell::new(Vec::new());
+
+        let data = fetch(&cache, at(1), 2.0, &calls);
+        assert_eq!(*calls.borrow(), vec![at(1)]);
+        assert_eq!(data.len(), 10);
+        assert!(data.iter().all(|p| p.price_usd == 2.0));
+    }
+
+    #[test]
+    fn full_refresh_refetches_everything() {
+        let cache = temp_cache("fetch_full_refresh");
+        cache.store("gold", at(1), &prices(1, 10, 1.0)).unwrap();
+        let calls = RefCell::new(Vec::new());
+
+        let data = fetch(&cache.clone().with_full_refresh(true), at(1), 2.0, &calls);
+        assert_eq!(*calls.borrow(), vec![at(1)]);
+        assert!(data.iter().all(|p| p.price_usd == 2.0));
+        assert!(cache.load("gold", at(1)).unwrap().data.iter().all(|p| p.price_usd == 2.0));
+    }
+}
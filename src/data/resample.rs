@@ -0,0 +1,255 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use std::collections::HashMap;
+use crate::models::Asset;
+use crate::data::{Ohlcv, PriceData};
+
+/// How often a series has a value; only daily prices are fetched, the rest are resampled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Frequency {
+    #[default]
+    Daily,
+    Weekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl Frequency {
+    pub fn all() -> [Frequency; 5] {
+        [Frequency::Daily, Frequency::Weekly, Frequency::Monthly, Frequency::Quarterly, Frequency::Yearly]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Daily => "Daily",
+            Self::Weekly => "Weekly",
+            Self::Monthly => "Monthly",
+            Self::Quarterly => "Quarterly",
+            Self::Yearly => "Yearly",
+        }
+    }
+
+    /// Length of one period, e.g. "week" in "14-week ATR"
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Self::Daily => "day",
+            Self::Weekly => "week",
+            Self::Monthly => "month",
+            Self::Quarterly => "quarter",
+            Self::Yearly => "year",
+        }
+    }
+
+    /// Average length of one period in days, for sizing bars on a date axis
+    pub fn days(&self) -> f64 {
+        match self {
+            Self::Daily => 1.0,
+            Self::Weekly => 7.0,
+            Self::Monthly => 365.25 / 12.0,
+            Self::Quarterly => 365.25 / 4.0,
+            Self::Yearly => 365.25,
+        }
+    }
+
+    /// "daily", "weekly", "monthly", "quarterly" or "yearly"
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "daily" => Some(Self::Daily),
+            "weekly" => Some(Self::Weekly),
+            "monthly" => Some(Self::Monthly),
+            "quarterly" => Some(Self::Quarterly),
+            "yearly" => Some(Self::Yearly),
+            _ => None,
+        }
+    }
+
+    /// First day of the period `date` falls in; weeks start on Monday
+    pub fn period_start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Daily => date,
+            Self::Weekly => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Self::Monthly => date.with_day(1).unwrap(),
+            Self::Quarterly => NaiveDate::from_ymd_opt(date.year(), (date.month0() / 3) * 3 + 1, 1).unwrap(),
+            Self::Yearly => NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap(),
+        }
+    }
+}
+
+/// How the daily prices in a period become one value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Aggregation {
+    /// The last close in the period
+    #[default]
+    Last,
+    /// The average close over the period
+    Mean,
+    /// The last close, plus an open/high/low/close bar from the daily bars
+    Ohlc,
+}
+
+impl Aggregation {
+    pub fn all() -> [Aggregation; 3] {
+        [Aggregation::Last, Aggregation::Mean, Aggregation::Ohlc]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Last => "Last close",
+            Self::Mean => "Average",
+            Self::Ohlc => "OHLC bars",
+        }
+    }
+
+    /// "last", "mean" or "ohlc"
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "last" => Some(Self::Last),
+            "mean" => Some(Self::Mean),
+            "ohlc" => Some(Self::Ohlc),
+            _ => None,
+        }
+    }
+}
+
+/// One point per period of `frequency`, dated on the first day of the period so series
+/// resampled the same way share dates. `data` must be sorted by date.
+pub fn resample(data: &[PriceData], frequency: Frequency, aggregation: Aggregation) -> Vec<PriceData> {
+    if frequency == Frequency::Daily {
+        return data.to_vec();
+    }
+    data.chunk_by(|a, b| frequency.period_start(a.date) == frequency.period_start(b.date))
+        .map(|period| {
+            let last = &period[period.len() - 1];
+            let price_usd = match aggregation {
+                Aggregation::Last | Aggregation::Ohlc => last.price_usd,
+                Aggregation::Mean => period.iter().map(|p| p.price_usd).sum::<f64>() / period.len() as f64,
+            };
+            let ohlcv = if aggregation == Aggregation::Ohlc { combine_bars(period) } else { None };
+            PriceData { date: frequency.period_start(last.date), price_usd, source: last.source, ohlcv }
+        })
+        .collect()
+}
+
+/// `resample` for dated values without bars, such as exchange rates; `Ohlc` keeps the last value
+pub fn resample_values(points: &[(NaiveDate, f64)], frequency: Frequency, aggregation: Aggregation) -> Vec<(NaiveDate, f64)> {
+    if frequency == Frequency::Daily {
+        return points.to_vec();
+    }
+    points.chunk_by(|a, b| frequency.period_start(a.0) == frequency.period_start(b.0))
+        .map(|period| {
+            let value = match aggregation {
+                Aggregation::Last | Aggregation::Ohlc => period[period.len() - 1].1,
+                Aggregation::Mean => period.iter().map(|(_, v)| v).sum::<f64>() / period.len() as f64,
+            };
+            (frequency.period_start(period[0].0), value)
+        })
+        .collect()
+}
+
+/// `resample` for every asset
+pub fn resample_all(data: &HashMap<Asset, Vec<PriceData>>, frequency: Frequency, aggregation: Aggregation) -> HashMap<Asset, Vec<PriceData>> {
+    data.iter()
+        .map(|(asset, prices)| (*asset, resample(prices, frequency, aggregation)))
+        .collect()
+}
+
+// One bar from the daily bars of a period; None when the source only has closes
+fn combine_bars(period: &[PriceData]) -> Option<Ohlcv> {
+    let mut bars = period.iter().filter_map(|p| p.ohlcv);
+    let first = bars.next()?;
+    Some(bars.fold(first, |bar, next| Ohlcv {
+        open: bar.open,
+        high: bar.high.max(next.high),
+        low: bar.low.min(next.low),
+        close: next.close,
+        volume: match (bar.volume, next.volume) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        },
+        adj_close: next.adj_close,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::fixtures::{date, day, point};
+
+    // A daily bar on January `d`, 2024, priced at its close
+    fn bar(d: u32, open: f64, high: f64, low: f64, close: f64, volume: Option<f64>) -> PriceData {
+        PriceData { ohlcv: Some(Ohlcv { open, high, low, close, volume, adj_close: None }), ..point(day(d), close) }
+    }
+
+    #[test]
+    fn periods_start_on_monday_quarter_and_year_boundaries() {
+        // Sunday Jan 7 2024 is in the week of Monday Jan 1, Monday Jan 8 starts the next
+        assert_eq!(Frequency::Weekly.period_start(date(2024, 1, 7)), date(2024, 1, 1));
+        assert_eq!(Frequency::Weekly.period_start(date(2024, 1, 8)), date(2024, 1, 8));
+        // A week spanning New Year starts in the old year
+        assert_eq!(Frequency::Weekly.period_start(date(2025, 1, 1)), date(2024, 12, 30));
+        assert_eq!(Frequency::Monthly.period_start(date(2024, 2, 29)), date(2024, 2, 1));
+        assert_eq!(Frequency::Quarterly.period_start(date(2024, 3, 31)), date(2024, 1, 1));
+        assert_eq!(Frequency::Quarterly.period_start(date(2024, 4, 1)), date(2024, 4, 1));
+        assert_eq!(Frequency::Quarterly.period_start(date(2024, 12, 31)), date(2024, 10, 1));
+        assert_eq!(Frequency::Yearly.period_start(date(2024, 12, 31)), date(2024, 1, 1));
+        assert_eq!(Frequency::Yearly.period_start(date(2025, 1, 1)), date(2025, 1, 1));
+    }
+
+    #[test]
+    fn last_and_mean_per_week() {
+        // Thursday Jan 4 to Wednesday Jan 10 2024: two weeks
+        let data: Vec<PriceData> = (4..=10).map(|d| point(date(2024, 1, d), d as f64)).collect();
+
+        let last = resample(&data, Frequency::Weekly, Aggregation::Last);
+        assert_eq!(last.iter().map(|p| (p.date, p.price_usd)).collect::<Vec<_>>(),
+                   vec![(date(2024, 1, 1), 7.0), (date(2024, 1, 8), 10.0)]);
+
+        let mean = resample(&data, Frequency::Weekly, Aggregation::Mean);
+        assert_eq!(mean.iter().map(|p| (p.date, p.price_usd)).collect::<Vec<_>>(),
+                   vec![(date(2024, 1, 1), 5.5), (date(2024, 1, 8), 9.0)]);
+    }
+
+    #[test]
+    fn quarters_and_years_split_at_their_boundaries() {
+        let data = vec![
+            point(date(2023, 12, 29), 1.0),
+            point(date(2024, 3, 29), 2.0),
+            point(date(2024, 4, 1), 3.0),
+        ];
+        let quarters = resample(&data, Frequency::Quarterly, Aggregation::Last);
+        assert_eq!(quarters.iter().map(|p| (p.date, p.price_usd)).collect::<Vec<_>>(),
+                   vec![(date(2023, 10, 1), 1.0), (date(2024, 1, 1), 2.0), (date(2024, 4, 1), 3.0)]);
+        let years = resample(&data, Frequency::Yearly, Aggregation::Mean);
+        assert_eq!(years.iter().map(|p| (p.date, p.price_usd)).collect::<Vec<_>>(),
+                   vec![(date(2023, 1, 1), 1.0), (date(2024, 1, 1), 2.5)]);
+    }
+
+    #[test]
+    fn ohlc_combines_daily_bars() {
+        let data = vec![
+            bar(1, 10.0, 12.0, 9.0, 11.0, Some(100.0)),
+            bar(2, 11.0, 15.0, 10.0, 13.0, None),
+            bar(3, 13.0, 14.0, 8.0, 12.0, Some(50.0)),
+        ];
+        let weekly = resample(&data, Frequency::Weekly, Aggregation::Ohlc);
+        assert_eq!(weekly.len(), 1);
+        assert_eq!(weekly[0].price_usd, 12.0);
+        let combined = weekly[0].ohlcv.unwrap();
+        assert_eq!((combined.open, combined.high, combined.low, combined.close), (10.0, 15.0, 8.0, 12.0));
+        // Days without volume don't erase the others'
+        assert_eq!(combined.volume, Some(150.0));
+
+        let no_volume: Vec<PriceData> = data.iter()
+            .map(|p| PriceData { ohlcv: p.ohlcv.map(|b| Ohlcv { volume: None, ..b }), ..p.clone() })
+            .collect();
+        assert_eq!(resample(&no_volume, Frequency::Weekly, Aggregation::Ohlc)[0].ohlcv.unwrap().volume, None);
+    }
+
+    #[test]
+    fn ohlc_without_bars_keeps_closes() {
+        let data = vec![point(date(2024, 1, 1), 1.0), point(date(2024, 1, 2), 2.0)];
+        let weekly = resample(&data, Frequency::Weekly, Aggregation::Ohlc);
+        assert_eq!(weekly[0].price_usd, 2.0);
+        assert!(weekly[0].ohlcv.is_none());
+    }
+}
@@ -0,0 +1,85 @@
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use anyhow::Result;
+use crate::models::Asset;
+use crate::data::PriceData;
+
+/// What a source can provide for a given asset
+#[derive(Debug, Clone, Copy)]
+pub struct SourceCoverage {
+    /// Earliest date the source has data for, if known
+    pub earliest: Option<NaiveDate>,
+    /// True when the data is generated rather than observed (e.g. sample data)
+    pub synthetic: bool,
+}
+
+/// A provider of daily price history for one or more assets
+#[async_trait]
+pub trait PriceSource: Send + Sync {
+    /// Human readable name used in logs and the UI
+    fn name(&self) -> &'static str;
+
+    /// Coverage for `asset`, or `None` if the source cannot provide it
+    fn coverage(&self, asset: Asset) -> Option<SourceCoverage>;
+
+    fn supports(&self, asset: Asset) -> bool {
+        self.coverage(asset).is_some()
+    }
+
+    /// Whether fetched prices should go through the price cache; local files are
+    /// cheap to reread and may be edited at any time
+    fn cacheable(&self, _asset: Asset) -> bool {
+        true
+    }
+
+    /// Fetch daily prices for `asset` in its quote currency (USD unless configured), sorted by date
+    async fn fetch_asset(&self, asset: Asset, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<PriceData>>;
+
+    /// Fetch daily USD -> `currency` exchange rates, stored in `price_usd`
+    async fn fetch_exchange_rate(&self, currency: &str, _start: DateTime<Utc>, _end: DateTime<Utc>) -> Result<Vec<PriceData>> {
+        Err(anyhow::anyhow!("{} does not provide {} exchange rates", self.name(), currency))
+    }
+}
+
+/// Ordered list of sources; earlier sources take priority for each asset
+pub struct SourceChain {
+    sources: Vec<Box<dyn PriceSource>>,
+}
+
+impl SourceChain {
+    pub fn new() -> Self {
+        Self { sources: Vec::new() }
+    }
+
+    /// Append a source with lower priority than every source already in the chain
+    pub fn with_source(mut self, source: impl PriceSource + 'static) -> Self {
+        self.sources.push(Box::new(source));
+        self
+    }
+
+    pub fn sources(&self) -> impl Iterator<Item = &dyn PriceSource> {
+        self.sources.iter().map(|s| s.as_ref())
+    }
+
+    /// Sources able to provide `asset`, in priority order
+    pub fn sources_for(&self, asset: Asset) -> impl Iterator<Item = &dyn PriceSource> {
+        self.sources().filter(move |s| s.supports(asset))
+    }
+}
+
+impl Default for SourceChain {
+    /// Yahoo Finance first, then CoinGecko (with sample commodity data), then CoinDesk,
+    /// then CSV files (CPI and other configured series)
+    fn default() -> Self {
+        use crate::yahoo_data::YahooDataFetcher;
+        use crate::alternative_data::AlternativeDataFetcher;
+        use crate::bitcoin_history::CoinDeskSource;
+        use crate::csv_data::CsvSource;
+
+        Self::new()
+            .with_source(YahooDataFetcher::new())
+            .with_source(AlternativeDataFetcher::new())
+            .with_source(CoinDeskSource)
+            .with_source(CsvSource::new())
+    }
+}
@@ -0,0 +1,234 @@
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::HashMap;
+use crate::models::{Asset, Currency};
+use crate::data::PriceData;
+use crate::data::fx::ExchangeRates;
+use crate::data::align::{Alignment, DateIndex};
+use crate::data::resample::Frequency;
+use crate::series::{self, SeriesPoint, SeriesTable};
+
+/// A fixed amount of fiat put in at the start of every period
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DcaPlan {
+    pub amount: f64,
+    pub currency: Currency,
+    pub frequency: Frequency,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl Default for DcaPlan {
+    fn default() -> Self {
+        Self { amount: 100.0, currency: Currency::USD, frequency: Frequency::Monthly, from: None, to: None }
+    }
+}
+
+/// What a line's contributions buy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Holding {
+    Bitcoin,
+    Asset(Asset),
+    /// Cash kept in a currency, converted from the contribution currency when put in
+    Cash(Currency),
+}
+
+impl Holding {
+    /// Column prefix, e.g. "btc", "gold" or "cad"
+    pub fn key(&self) -> String {
+        match self {
+            Self::Bitcoin => "btc".to_string(),
+            Self::Asset(asset) => asset.id().to_string(),
+            Self::Cash(currency) => currency.code.to_ascii_lowercase(),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Self::Bitcoin => "Bitcoin".to_string(),
+            Self::Asset(asset) => asset.base_name().to_string(),
+            Self::Cash(currency) => format!("{} cash", currency.code),
+        }
+    }
+
+    /// Units held, e.g. "BTC", "oz" or "CAD"
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Self::Bitcoin => "BTC",
+            Self::Asset(asset) => asset.unit(),
+            Self::Cash(currency) => currency.code,
+        }
+    }
+}
+
+/// A line's position on one date
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct DcaPoint {
+    pub date: NaiveDate,
+    /// Total put in so far, in the plan's currency
+    pub contributed: f64,
+    /// Units bought so far (BTC, units of the asset or cash)
+    pub held: f64,
+    /// Worth of the holdings in the plan's currency
+    pub value: f64,
+    /// Worth of the holdings in units of the measured asset
+    pub value_in_asset: f64,
+}
+
+/// The same contributions put into one holding
+#[derive(Debug, Clone)]
+pub struct DcaLine {
+    pub holding: Holding,
+    pub points: Vec<DcaPoint>,
+}
+
+impl DcaLine {
+    pub fn last(&self) -> Option<&DcaPoint> {
+        self.points.last()
+    }
+
+    /// Gain on everything put in, in the plan's currency
+    pub fn return_pct(&self) -> Option<f64> {
+        let last = self.last()?;
+        (last.contributed > 0.0).then(|| (last.value / last.contributed - 1.0) * 100.0)
+    }
+}
+
+/// Put `plan.amount` into Bitcoin, into `asset` itself and into cash in USD, the plan's
+/// currency and each of `currencies` on the first Bitcoin date of every period, and value
+/// each on every date in the plan's currency and in units of `asset`. Dates without an asset
+/// price (matched by `alignment`) or an exchange rate for any of the lines are skipped.
+pub fn simulate(
+    data: &HashMap<Asset, Vec<PriceData>>,
+    fx: &ExchangeRates,
+    asset: Asset,
+    plan: &DcaPlan,
+    currencies: &[Currency],
+    alignment: Alignment,
+) -> Vec<DcaLine> {
+    let (Some(btc_data), Some(asset_data)) = (data.get(&Asset::BITCOIN), data.get(&asset)) else {
+        return Vec::new();
+    };
+    let mut holdings = vec![Holding::Bitcoin, Holding::Asset(asset), Holding::Cash(Currency::USD)];
+    for &currency in std::iter::once(&plan.currency).chain(currencies) {
+        if !holdings.contains(&Holding::Cash(currency)) {
+            holdings.push(Holding::Cash(currency));
+        }
+    }
+
+    let btc_data: Vec<&PriceData> = btc_data.iter()
+        .filter(|p| series::in_range(p.date, plan.from, plan.to))
+        .collect();
+    let prices = DateIndex::from_prices(asset_data);
+    let asset_prices = prices.join(btc_data.iter().map(|p| p.date), alignment);
+    let rates = fx.rates(plan.currency, btc_data.iter().map(|p| p.date));
+
+    let mut lines: Vec<DcaLine> = holdings.iter().map(|&holding| DcaLine { holding, points: Vec::new() }).collect();
+    let mut held = vec![0.0; holdings.len()];
+    let mut contributed = 0.0;
+    let mut last_period = None;
+    for ((btc_point, (date, asset_usd)), rate) in btc_data.iter().zip(asset_prices).zip(rates) {
+        let (Some(asset_usd), Some(rate)) = (asset_usd, rate.filter(|r| *r > 0.0)) else {
+            continue;
+        };
+        if btc_point.price_usd <= 0.0 || asset_usd <= 0.0 {
+            continue;
+        }
+        // USD price of one unit of each holding on this date
+        let unit_usd = |holding: &Holding| match holding {
+            Holding::Bitcoin => Some(btc_point.price_usd),
+            Holding::Asset(_) => Some(asset_usd),
+            Holding::Cash(currency) => fx.rate(*currency, date).filter(|r| *r > 0.0).map(|r| 1.0 / r),
+        };
+        let Some(unit_prices) = holdings.iter().map(unit_usd).collect::<Option<Vec<f64>>>() else {
+            continue;
+        };
+
+        let period = plan.frequency.period_start(date);
+        if last_period != Some(period) {
+            last_period = Some(period);
+            contributed += plan.amount;
+            let usd = plan.amount / rate;
+            for (held, unit_price) in held.iter_mut().zip(&unit_prices) {
+                *held += usd / unit_price;
+            }
+        }
+        for ((line, held), unit_price) in lines.iter_mut().zip(&held).zip(&unit_prices) {
+            let value_usd = held * unit_price;
+            line.points.push(DcaPoint {
+                date,
+                contributed,
+                held: *held,
+                value: value_usd * rate,
+                value_in_asset: value_usd / asset_usd,
+            });
+        }
+    }
+    lines.retain(|line| !line.points.is_empty());
+    lines
+}
+
+/// One row per date: `contributed` in the plan's currency, then each line's units
+/// `{key}_held`, `{key}_value` in the plan's currency and `{key}_in_{asset}`
+pub fn table(lines: &[DcaLine], asset: Asset) -> SeriesTable {
+    let mut series: Vec<(String, Vec<SeriesPoint>)> = Vec::new();
+    if let Some(line) = lines.first() {
+        series.push(("contributed".to_string(), line.points.iter().map(|p| (p.date, p.contributed)).collect()));
+    }
+    for line in lines {
+        let key = line.holding.key();
+        let column = |value: fn(&DcaPoint) -> f64| line.points.iter().map(|p| (p.date, value(p))).collect();
+        series.push((format!("{}_held", key), column(|p| p.held)));
+        series.push((format!("{}_value", key), column(|p| p.value)));
+        series.push((format!("{}_in_{}", key, asset.id()), column(|p| p.value_in_asset)));
+    }
+    SeriesTable::from_series(series)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::align::AlignPolicy;
+    use crate::data::fixtures::{daily, day};
+
+    fn inputs(cad_rates: &[f64]) -> (HashMap<Asset, Vec<PriceData>>, ExchangeRates, Asset) {
+        let gold = Asset::from_id("gold").unwrap();
+        let data = HashMap::from([
+            (Asset::BITCOIN, daily(&[40000.0, 42000.0, 44000.0, 46000.0])),
+            (gold, daily(&[2000.0, 2010.0, 2020.0, 2030.0])),
+        ]);
+        let mut fx = ExchangeRates::default();
+        fx.insert(Currency::from_code("CAD").unwrap(), &daily(cad_rates));
+        (data, fx, gold)
+    }
+
+    #[test]
+    fn dates_with_a_zero_rate_are_skipped() {
+        let (data, fx, gold) = inputs(&[1.35, 0.0, 1.36, 1.34]);
+        let cad = Currency::from_code("CAD").unwrap();
+        let plan = DcaPlan { currency: cad, frequency: Frequency::Daily, ..DcaPlan::default() };
+        let lines = simulate(&data, &fx, gold, &plan, &[], Alignment::new(AlignPolicy::Exact, 0));
+        assert_eq!(lines.len(), 4);
+        for line in &lines {
+            let dates: Vec<NaiveDate> = line.points.iter().map(|p| p.date).collect();
+            assert_eq!(dates, vec![day(1), day(3), day(4)]);
+            assert!(line.points.iter().all(|p| p.value.is_finite() && p.held.is_finite()));
+        }
+        let cash = lines.iter().find(|line| line.holding == Holding::Cash(cad)).unwrap();
+        assert!((cash.last().unwrap().held - 300.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn cash_lines_for_usd_plan_and_enabled_currencies() {
+        let (data, fx, gold) = inputs(&[1.35, 1.35, 1.36, 1.34]);
+        let cad = Currency::from_code("CAD").unwrap();
+        let plan = DcaPlan { frequency: Frequency::Daily, ..DcaPlan::default() };
+        let lines = simulate(&data, &fx, gold, &plan, &[cad, Currency::USD], Alignment::new(AlignPolicy::Exact, 0));
+        let holdings: Vec<Holding> = lines.iter().map(|line| line.holding).collect();
+        assert_eq!(holdings, vec![Holding::Bitcoin, Holding::Asset(gold), Holding::Cash(Currency::USD), Holding::Cash(cad)]);
+        // 100 USD a day turned into CAD at each day's rate, valued back in USD at the last one
+        let cash = lines[3].last().unwrap();
+        assert!((cash.held - (135.0 + 135.0 + 136.0 + 134.0)).abs() < 1e-9);
+        assert!((cash.value - cash.held / 1.34).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,160 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use parquet::basic::{Compression, LogicalType, Repetition, Type as PhysicalType};
+use parquet::data_type::{DoubleType, Int32Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::types::Type;
+use crate::series::SeriesTable;
+use crate::stats::CorrelationMatrix;
+
+/// File formats a `SeriesTable` can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn all() -> [ExportFormat; 3] {
+        [ExportFormat::Csv, ExportFormat::Json, ExportFormat::Parquet]
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "json" => Some(Self::Json),
+            "parquet" => Some(Self::Parquet),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension().and_then(|ext| ext.to_str()).and_then(Self::parse)
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+            Self::Parquet => "parquet",
+        }
+    }
+}
+
+/// Write `table` to `path`, creating its directory if needed
+pub fn export(table: &SeriesTable, path: &Path, format: ExportFormat) -> Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    match format {
+        ExportFormat::Csv => write_csv(file, table),
+        ExportFormat::Json => write_json(file, table),
+        ExportFormat::Parquet => write_parquet(file, table),
+    }
+    .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// One row per date; missing values are empty cells
+pub fn write_csv(out: impl Write, table: &SeriesTable) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(std::iter::once("date").chain(table.columns.iter().map(String::as_str)))?;
+    for (date, values) in &table.rows {
+        let mut record = vec![date.format("%Y-%m-%d").to_string()];
+        record.extend(values.iter().map(|v| v.map(|v| v.to_string()).unwrap_or_default()));
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// An array of `{"date": ..., "<column>": value or null}` objects
+pub fn write_json(mut out: impl Write, table: &SeriesTable) -> Result<()> {
+    let rows: Vec<serde_json::Value> = table.rows.iter()
+        .map(|(date, values)| {
+            let mut row = serde_json::Map::new();
+            row.insert("date".to_string(), date.format("%Y-%m-%d").to_string().into());
+            for (column, value) in table.columns.iter().zip(values) {
+                row.insert(column.clone(), value.map_or(serde_json::Value::Null, Into::into));
+            }
+            serde_json::Value::Object(row)
+        })
+        .collect();
+    serde_json::to_writer_pretty(&mut out, &rows)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// The matrix with an `asset` column of ids and one column per asset, empty where there
+/// is no correlation
+pub fn write_matrix_csv(out: impl Write, matrix: &CorrelationMatrix) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(std::iter::once("asset").chain(matrix.assets.iter().map(|a| a.id())))?;
+    for (asset, values) in matrix.assets.iter().zip(&matrix.values) {
+        let mut record = vec![asset.id().to_string()];
+        record.extend(values.iter().map(|v| v.map(|v| v.to_string()).unwrap_or_default()));
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// `{"assets": [...], "correlations": [[...]], "counts": [[...]]}`, null where there is no correlation
+pub fn write_matrix_json(mut out: impl Write, matrix: &CorrelationMatrix) -> Result<()> {
+    let json = serde_json::json!({
+        "assets": matrix.assets.iter().map(|a| a.id()).collect::<Vec<_>>(),
+        "correlations": matrix.values,
+        "counts": matrix.counts,
+    });
+    serde_json::to_writer_pretty(&mut out, &json)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// A `date` column (DATE logical type) and one optional double column per series
+pub fn write_parquet(out: File, table: &SeriesTable) -> Result<()> {
+    let mut fields = vec![Arc::new(
+        Type::primitive_type_builder("date", PhysicalType::INT32)
+            .with_repetition(Repetition::REQUIRED)
+            .with_logical_type(Some(LogicalType::Date))
+            .build()?,
+    )];
+    for column in &table.columns {
+        fields.push(Arc::new(
+            Type::primitive_type_builder(column, PhysicalType::DOUBLE)
+                .with_repetition(Repetition::OPTIONAL)
+                .build()?,
+        ));
+    }
+    let schema = Arc::new(Type::group_type_builder("series").with_fields(fields).build()?);
+    let properties = Arc::new(WriterProperties::builder().set_compression(Compression::SNAPPY).build());
+    let mut writer = SerializedFileWriter::new(out, schema, properties)?;
+
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+    let dates: Vec<i32> = table.rows.keys().map(|date| (*date - epoch).num_days() as i32).collect();
+
+    let mut row_group = writer.next_row_group()?;
+    let mut index = 0;
+    while let Some(mut column) = row_group.next_column()? {
+        if index == 0 {
+            column.typed::<Int32Type>().write_batch(&dates, None, None)?;
+        } else {
+            // Only present values are written, definition levels mark the missing ones
+            let cells = table.rows.values().map(|values| values[index - 1]);
+            let values: Vec<f64> = cells.clone().flatten().collect();
+            let levels: Vec<i16> = cells.map(|v| v.is_some() as i16).collect();
+            column.typed::<DoubleType>().write_batch(&values, Some(&levels), None)?;
+        }
+        column.close()?;
+        index += 1;
+    }
+    row_group.close()?;
+    writer.close()?;
+    Ok(())
+}
@@ -2,129 +2,1391 @@ mod models;
 mod data;
 mod yahoo_data;
 mod alternative_data;
+mod bitcoin_history;
+mod csv_data;
+mod series;
+mod export;
+mod report;
+mod render;
+mod stats;
+mod dca;
+mod portfolio;
+mod server;
+mod cli;
 
 use eframe::egui;
-use egui_plot::{Line, Plot, PlotPoints, Legend};
+use egui_plot::{BoxElem, BoxPlot, BoxSpread, Line, MarkerShape, Plot, PlotPoints, Points, Legend, VLine};
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::rc::Rc;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::{Duration, Instant};
 use chrono::NaiveDate;
-use crate::models::{Asset, RepresentationMode};
-use crate::data::{PriceData, get_historical_data};
+use crate::models::{Asset, ChartStyle, Currency, RepresentationMode};
+use crate::models::registry::AssetRegistry;
+use crate::models::basket::{Basket, WEIGHT_TOTAL};
+use crate::data::{Ohlcv, PriceData, LoadProgress, create_consumer_basket, get_historical_data, source_ranges};
+use crate::data::cache::PriceCache;
+use crate::data::fx::ExchangeRates;
+use crate::data::align::{AlignPolicy, Alignment, Calendar};
+use crate::data::resample::{self, Aggregation, Frequency};
+use crate::series::{ChartOptions, SeriesPoint, BASKET_COLORS, BTC_COLOR};
+use crate::export::ExportFormat;
+use crate::report::HtmlReport;
+use crate::stats::{CorrelationMatrix, LineStats, RollingMetric, RollingPoint, ROLLING_WINDOWS};
+use crate::dca::{DcaLine, DcaPlan, Holding};
+use crate::portfolio::{Instrument, Portfolio, PortfolioHistory, Position};
+
+// Per-asset state of the background data load
+#[derive(Debug, Clone)]
+enum LoadStatus {
+    Fetching(&'static str),
+    Retrying(&'static str),
+    Loaded(usize),
+    Failed(String),
+}
+
+// Choices offered for the auto-refresh interval
+const AUTO_REFRESH_OPTIONS: [(Option<Duration>, &str); 4] = [
+    (None, "Off"),
+    (Some(Duration::from_secs(15 * 60)), "Every 15 minutes"),
+    (Some(Duration::from_secs(60 * 60)), "Every hour"),
+    (Some(Duration::from_secs(6 * 60 * 60)), "Every 6 hours"),
+];
+
+// Dated values as plot points, x in days since the Unix epoch
+fn to_plot_points(points: Vec<SeriesPoint>) -> Vec<[f64; 2]> {
+    points.into_iter()
+        .map(|(date, value)| {
+            let x = date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp() as f64 / 86400.0;
+            [x, value]
+        })
+        .collect()
+}
+
+// Percent change of each visible point from the first one in `x_bounds`
+fn percent_change(points: &[[f64; 2]], x_bounds: [f64; 2]) -> Vec<[f64; 2]> {
+    let base_idx = points.iter().position(|p| p[0] >= x_bounds[0]).unwrap_or(0);
+    let base = points.get(base_idx).map(|p| p[1]).unwrap_or(1.0);
+    points.iter()
+        .skip(base_idx)
+        .filter(|p| p[0] >= x_bounds[0] && p[0] <= x_bounds[1])
+        .map(|p| [p[0], ((p[1] / base) - 1.0) * 100.0])
+        .collect()
+}
+
+// Date of a plot x (days since the Unix epoch)
+fn plot_x_date(x: f64) -> NaiveDate {
+    NaiveDate::from_ymd_opt(1970, 1, 1).unwrap() + chrono::Duration::days(x.round() as i64)
+}
+
+// Purchasing power (units of the asset) of the points within `x_bounds`, whichever mode they are in
+fn visible_units(points: &[[f64; 2]], x_bounds: [f64; 2], mode: RepresentationMode) -> Vec<SeriesPoint> {
+    points.iter()
+        .filter(|p| p[0] >= x_bounds[0] && p[0] <= x_bounds[1])
+        .map(|p| (plot_x_date(p[0]), match mode {
+            RepresentationMode::UnitsPerCurrency => p[1],
+            RepresentationMode::PricePerUnit => 1.0 / p[1],
+        }))
+        .collect()
+}
+
+// Points of `pct` at the `carried` x positions (both sorted by x), for marking them
+fn carried_points(pct: &[[f64; 2]], carried: &[f64]) -> Vec<[f64; 2]> {
+    if carried.is_empty() {
+        return Vec::new();
+    }
+    pct.iter()
+        .filter(|p| carried.binary_search_by(|x| x.total_cmp(&p[0])).is_ok())
+        .copied()
+        .collect()
+}
+
+// Open circles on carried points, sharing one legend entry across lines
+fn carried_markers(points: Vec<[f64; 2]>, (r, g, b): (u8, u8, u8)) -> Points {
+    Points::new(PlotPoints::from(points))
+        .name("Carried over non-trading days")
+        .shape(MarkerShape::Circle)
+        .filled(false)
+        .radius(2.5)
+        .color(egui::Color32::from_rgb(r, g, b))
+}
+
+// Where an export named `file_name` is written: $EXPORT_DIR, by default ./exports
+fn export_path(file_name: &str) -> PathBuf {
+    let dir = std::env::var("EXPORT_DIR").map(PathBuf::from).unwrap_or_else(|_| PathBuf::from("exports"));
+    dir.join(file_name)
+}
+
+// Inputs the chart lines are computed from; the lines are recomputed when any changes
+#[derive(Clone, PartialEq)]
+struct SeriesKey {
+    asset: Asset,
+    mode: RepresentationMode,
+    alignment: Alignment,
+    frequency: Frequency,
+    aggregation: Aggregation,
+    currencies: Vec<Currency>,
+    compared: Vec<Asset>,
+    data_version: u64,
+}
+
+// Plot points of every line of the % change chart
+struct ChartSeries {
+    key: SeriesKey,
+    btc: Vec<[f64; 2]>,
+    btc_carried: Vec<f64>, // x of Bitcoin points using a price carried over a non-trading day
+    usd: Vec<[f64; 2]>,
+    currencies: Vec<(Currency, Vec<[f64; 2]>)>,
+    baskets: Vec<(Asset, Vec<[f64; 2]>, Vec<f64>)>, // Points and carried x, like `btc`
+    full_range: [f64; 2], // First and last Bitcoin date, x in days since the Unix epoch
+}
+
+// The lines as % change within `x_bounds`, recomputed when the slider moves
+struct ChartChanges {
+    series: Rc<ChartSeries>,
+    x_bounds: [f64; 2],
+    btc: Vec<[f64; 2]>,
+    usd: Vec<[f64; 2]>,
+    currencies: Vec<(Currency, Vec<[f64; 2]>)>,
+    baskets: Vec<(Asset, Vec<[f64; 2]>)>,
+    btc_carried: Vec<[f64; 2]>,
+    baskets_carried: Vec<Vec<[f64; 2]>>, // One per basket, in the same order
+    y_bounds: [f64; 2], // Every visible value with 10% padding
+    stats: Vec<LineStats>, // BTC, USD and each currency in units of the asset
+}
+
+impl ChartChanges {
+    fn new(series: Rc<ChartSeries>, x_bounds: [f64; 2]) -> Self {
+        let btc = percent_change(&series.btc, x_bounds);
+        let usd = percent_change(&series.usd, x_bounds);
+        let currencies: Vec<(Currency, Vec<[f64; 2]>)> = series.currencies.iter()
+            .map(|(currency, points)| (*currency, percent_change(points, x_bounds)))
+            .collect();
+        let baskets: Vec<(Asset, Vec<[f64; 2]>)> = series.baskets.iter()
+            .map(|(basket, points, _)| (*basket, percent_change(points, x_bounds)))
+            .collect();
+        let btc_carried = carried_points(&btc, &series.btc_carried);
+        let baskets_carried = baskets.iter()
+            .zip(&series.baskets)
+            .map(|((_, pct), (_, _, carried))| carried_points(pct, carried))
+            .collect();
+        
+        // Y-axis bounds from visible data, always including 0%
+        let mut min_y: f64 = 0.0;
+        let mut max_y: f64 = 0.0;
+        for points in [&btc, &usd].into_iter()
+            .chain(currencies.iter().map(|(_, pct)| pct))
+            .chain(baskets.iter().map(|(_, pct)| pct)) {
+            for p in points {
+                min_y = min_y.min(p[1]);
+                max_y = max_y.max(p[1]);
+            }
+        }
+        let y_padding = (max_y - min_y) * 0.1;
+        let y_bounds = if y_padding > 0.0 {
+            [min_y - y_padding, max_y + y_padding]
+        } else {
+            // If all values are the same, add some default padding
+            [min_y - 5.0, max_y + 5.0]
+        };
+        
+        let mode = series.key.mode;
+        let stats = [("BTC", &series.btc), ("USD", &series.usd)].into_iter()
+            .chain(series.currencies.iter().map(|(currency, points)| (currency.code, points)))
+            .filter_map(|(line, points)| LineStats::new(line, &visible_units(points, x_bounds, mode)))
+            .collect();
+        
+        Self { series, x_bounds, btc, usd, currencies, baskets, btc_carried, baskets_carried, y_bounds, stats }
+    }
+}
+
+// Rolling statistics of Bitcoin against the selected asset and each compared basket
+struct RollingSeries {
+    key: SeriesKey,
+    window: i64,
+    lines: Vec<(Asset, Vec<RollingPoint>)>,
+}
+
+// Prices at a non-daily chart frequency, resampled once per data version and frequency
+struct ResampledData {
+    key: (u64, Frequency, Aggregation),
+    data: HashMap<Asset, Vec<PriceData>>,
+}
+
+// Correlations over the visible range, recomputed when the data, frequency or range change
+struct CorrelationView {
+    key: (u64, Frequency, Aggregation, [f64; 2]),
+    matrix: CorrelationMatrix,
+}
+
+// DCA simulation of the selected asset over the visible range
+struct DcaView {
+    key: (u64, Asset, DcaPlan, Vec<Currency>, Alignment),
+    lines: Vec<DcaLine>,
+}
+
+// Valuation of the edited portfolio, recomputed when it or its prices change
+struct PortfolioView {
+    key: (u64, Portfolio, Currency, Asset, Alignment),
+    history: PortfolioHistory,
+}
 
 struct PurchasingPowerApp {
     data: HashMap<Asset, Vec<PriceData>>,
+    fx: ExchangeRates,
+    currencies: Vec<Currency>, // Enabled currencies drawn next to USD
     selected_commodity: Asset,
     representation_mode: RepresentationMode,
+    chart_style: ChartStyle,
+    candles_for_bitcoin: bool, // Candlesticks show Bitcoin instead of the selected asset
     date_slider_value: f32, // 0.0 to 1.0 representing start date position
+    load_status: HashMap<Asset, LoadStatus>,
+    loader: Option<Receiver<LoadProgress>>, // Set while a background load is running
+    refresh_pending: bool, // A refresh was asked for during a load, started once it finishes
+    cache: PriceCache,
+    auto_refresh: Option<Duration>,
+    last_refresh: Instant,
+    baskets: Vec<Basket>, // Consumer baskets as edited in the basket panel
+    basket_base_dates: HashMap<Asset, String>, // Base date text as typed, applied once it parses
+    compared_baskets: Vec<Asset>, // Extra baskets drawn next to the selected asset
+    export_status: Option<String>, // Result of the last export, shown next to the buttons
+    alignment: Alignment, // How asset prices are matched to dates they have no price on
+    frequency: Frequency, // Chart resolution; data is kept daily and resampled for drawing
+    aggregation: Aggregation,
+    data_version: u64, // Bumped whenever `data` or `fx` change, invalidating `chart_series`
+    chart_series: Option<Rc<ChartSeries>>,
+    chart_changes: Option<Rc<ChartChanges>>,
+    rolling_window: i64, // Days of history behind each rolling statistic
+    rolling_metric: RollingMetric,
+    rolling_series: Option<Rc<RollingSeries>>,
+    resampled: Option<Rc<ResampledData>>,
+    correlations: Option<Rc<CorrelationView>>,
+    dca_plan: DcaPlan, // Contributions of the DCA view; its dates follow the slider
+    dca_in_asset: bool, // DCA lines valued in units of the selected asset instead of the plan's currency
+    dca: Option<Rc<DcaView>>,
+    portfolio: Portfolio, // Holdings as edited; written to `portfolio_path` on save
+    saved_portfolio: Portfolio, // Holdings as last loaded or saved, to tell if there are unsaved edits
+    portfolio_path: PathBuf,
+    portfolio_dates: Vec<String>, // Position date text as typed, applied once it parses
+    portfolio_currency: Currency,
+    portfolio_in_asset: bool, // Portfolio valued in units of the selected asset instead of a currency
+    portfolio_view: Option<Rc<PortfolioView>>,
 }
 
 impl PurchasingPowerApp {
-    fn new() -> Self {
-        Self {
-            data: get_historical_data(),
-            selected_commodity: Asset::ConsumerBasket,
+    fn new(ctx: &egui::Context, cache: PriceCache, mut currencies: Vec<Currency>, portfolio_path: PathBuf) -> Self {
+        let portfolio = Portfolio::load(&portfolio_path).unwrap_or_else(|e| {
+            eprintln!("{:#}, starting with an empty portfolio", e);
+            Portfolio::default()
+        });
+        // Cash holdings need their exchange rates
+        for position in &portfolio.positions {
+            if let Instrument::Cash(currency) = position.instrument {
+                if currency != Currency::USD && !currencies.contains(&currency) {
+                    currencies.push(currency);
+                }
+            }
+        }
+        let mut app = Self {
+            data: HashMap::new(),
+            fx: ExchangeRates::default(),
+            currencies,
+            selected_commodity: Asset::baskets().next()
+                .or_else(|| Asset::tracked().iter().copied().find(|a| *a != Asset::BITCOIN))
+                .unwrap_or(Asset::BITCOIN),
             representation_mode: RepresentationMode::PricePerUnit,
+            chart_style: ChartStyle::PercentChange,
+            candles_for_bitcoin: false,
             date_slider_value: 0.75, // Start at 75% through the data (showing recent history)
+            load_status: HashMap::new(),
+            loader: None,
+            refresh_pending: false,
+            cache: cache.clone(),
+            auto_refresh: None,
+            last_refresh: Instant::now(),
+            baskets: Vec::new(),
+            basket_base_dates: HashMap::new(),
+            compared_baskets: Vec::new(),
+            export_status: None,
+            alignment: Alignment::default(),
+            frequency: Frequency::Daily,
+            aggregation: Aggregation::Last,
+            data_version: 0,
+            chart_series: None,
+            chart_changes: None,
+            rolling_window: 90,
+            rolling_metric: RollingMetric::Correlation,
+            rolling_series: None,
+            resampled: None,
+            correlations: None,
+            dca_plan: DcaPlan::default(),
+            dca_in_asset: true,
+            dca: None,
+            portfolio_dates: portfolio.positions.iter().map(|p| p.date.to_string()).collect(),
+            saved_portfolio: portfolio.clone(),
+            portfolio,
+            portfolio_path,
+            portfolio_currency: Currency::USD,
+            portfolio_in_asset: false,
+            portfolio_view: None,
+        };
+        for basket in AssetRegistry::global().baskets() {
+            app.set_basket(basket.clone());
         }
+        app.start_loading(ctx, cache);
+        app
     }
-
-    fn calculate_btc_values(&self) -> Vec<[f64; 2]> {
-        let btc_data = match self.data.get(&Asset::Bitcoin) {
-            Some(data) => data,
-            None => return Vec::new(),
-        };
-        let commodity_data = match self.data.get(&self.selected_commodity) {
-            Some(data) => data,
-            None => return Vec::new(),
+    
+    // Refetch recent days in the background; existing data stays on screen and is
+    // replaced asset by asset, so the selection and slider are untouched
+    fn refresh(&mut self, ctx: &egui::Context) {
+        // A second loader would fetch everything again alongside the first
+        if self.is_loading() {
+            self.refresh_pending = true;
+            return;
+        }
+        // Only the days since the last cached date are requested
+        let cache = self.cache.clone().for_refresh();
+        self.start_loading(ctx, cache);
+    }
+    
+    fn check_auto_refresh(&mut self, ctx: &egui::Context) {
+        let Some(interval) = self.auto_refresh else {
+            return;
         };
+        if self.is_loading() {
+            return;
+        }
+        
+        let elapsed = self.last_refresh.elapsed();
+        if elapsed >= interval {
+            eprintln!("Auto-refreshing price data");
+            self.refresh(ctx);
+        } else {
+            ctx.request_repaint_after(interval - elapsed);
+        }
+    }
+    
+    // Fetch data on a background thread so the window opens immediately
+    fn start_loading(&mut self, ctx: &egui::Context, cache: PriceCache) {
+        let (sender, receiver) = mpsc::channel();
+        let ctx = ctx.clone();
+        let currencies = self.currencies.clone();
         
-        let mut points = Vec::new();
-        let mut exact_matches = 0;
-        let mut nearest_matches = 0;
+        std::thread::spawn(move || {
+            let report = move |event: LoadProgress| {
+                // The app may have been closed, nothing to report to then
+                let _ = sender.send(event);
+                ctx.request_repaint();
+            };
+            get_historical_data(Some(&cache), &currencies, &report);
+        });
         
-        // Find matching dates
-        for btc_point in btc_data {
-            // First try exact match
-            if let Some(commodity_point) = commodity_data.iter().find(|p| p.date == btc_point.date) {
-                exact_matches += 1;
-                let value = match self.representation_mode {
-                    RepresentationMode::UnitsPerCurrency => btc_point.price_usd / commodity_point.price_usd,
-                    RepresentationMode::PricePerUnit => commodity_point.price_usd / btc_point.price_usd,
-                };
-                let x = btc_point.date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp() as f64 / 86400.0;
-                points.push([x, value]);
-            } else {
-                // If no exact match, find the nearest date
-                let mut nearest_date = None;
-                let mut min_days_diff = i64::MAX;
-                
-                for commodity_point in commodity_data {
-                    let days_diff = (btc_point.date - commodity_point.date).num_days().abs();
-                    if days_diff < min_days_diff {
-                        min_days_diff = days_diff;
-                        nearest_date = Some(commodity_point);
-                    }
+        self.load_status.clear();
+        self.loader = Some(receiver);
+        self.last_refresh = Instant::now();
+    }
+    
+    fn poll_loader(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.loader else {
+            return;
+        };
+        
+        let mut baskets_loaded = Vec::new();
+        loop {
+            match receiver.try_recv() {
+                Ok(LoadProgress::Fetching { asset, source }) => {
+                    self.load_status.insert(asset, LoadStatus::Fetching(source));
                 }
-                
-                if let Some(commodity_point) = nearest_date {
-                    // Only use nearest date if within 30 days
-                    if min_days_diff <= 30 {
-                        nearest_matches += 1;
-                        let value = match self.representation_mode {
-                            RepresentationMode::UnitsPerCurrency => btc_point.price_usd / commodity_point.price_usd,
-                            RepresentationMode::PricePerUnit => commodity_point.price_usd / btc_point.price_usd,
-                        };
-                        let x = btc_point.date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp() as f64 / 86400.0;
-                        points.push([x, value]);
+                Ok(LoadProgress::Retrying { asset, source }) => {
+                    self.load_status.insert(asset, LoadStatus::Retrying(source));
+                }
+                Ok(LoadProgress::Loaded { asset, data }) => {
+                    self.load_status.insert(asset, LoadStatus::Loaded(data.len()));
+                    if asset.is_basket() {
+                        // The loader uses the configured weights, rebuild with the edited ones
+                        baskets_loaded.push(asset);
+                    } else {
+                        self.data.insert(asset, data);
+                        self.data_version += 1;
                     }
                 }
+                Ok(LoadProgress::ExchangeRates { currency, rates }) => {
+                    self.fx.insert(currency, &rates);
+                    self.data_version += 1;
+                }
+                Ok(LoadProgress::Failed { asset, error }) => {
+                    self.load_status.insert(asset, LoadStatus::Failed(error));
+                }
+                Ok(LoadProgress::Finished) | Err(TryRecvError::Disconnected) => {
+                    self.loader = None;
+                    break;
+                }
+                Err(TryRecvError::Empty) => break,
             }
         }
         
-        // Debug output - only print when commodity changes or on first run
-        static mut LAST_COMMODITY: Option<Asset> = None;
-        unsafe {
-            if LAST_COMMODITY != Some(self.selected_commodity) {
-                println!("Commodity: {:?} - Exact matches: {}, Nearest matches: {}, Total points: {}", 
-                         self.selected_commodity, exact_matches, nearest_matches, points.len());
-                LAST_COMMODITY = Some(self.selected_commodity);
+        for basket in baskets_loaded {
+            self.rebuild_basket(basket);
+        }
+        if self.refresh_pending && !self.is_loading() {
+            self.refresh_pending = false;
+            self.refresh(ctx);
+        }
+    }
+    
+    fn is_loading(&self) -> bool {
+        self.loader.is_some()
+    }
+    
+    fn show_load_status(&self, ui: &mut egui::Ui) {
+        let loaded = self.load_status.values().filter(|s| matches!(s, LoadStatus::Loaded(_))).count();
+        let failed = self.load_status.values().filter(|s| matches!(s, LoadStatus::Failed(_))).count();
+        
+        ui.horizontal(|ui| {
+            if self.is_loading() {
+                ui.spinner();
+                ui.label(format!("Loading price data... {} of {} assets loaded", loaded, Asset::tracked().len()));
+            } else {
+                ui.label(format!("Loaded {} assets", loaded));
+            }
+            if failed > 0 {
+                ui.colored_label(egui::Color32::RED, format!("{} failed", failed));
+            }
+        });
+        
+        egui::CollapsingHeader::new("Data load status")
+            .default_open(self.is_loading() && self.data.is_empty())
+            .show(ui, |ui| {
+                for asset in Asset::tracked().iter().copied().chain(Asset::baskets()) {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{}:", asset.base_name()));
+                        match self.load_status.get(&asset) {
+                            Some(LoadStatus::Fetching(source)) => {
+                                ui.label(format!("fetching from {}", source));
+                            }
+                            Some(LoadStatus::Retrying(source)) => {
+                                ui.colored_label(egui::Color32::YELLOW, format!("retrying with {}", source));
+                            }
+                            Some(LoadStatus::Loaded(points)) => {
+                                ui.colored_label(egui::Color32::GREEN, format!("{} data points", points));
+                            }
+                            Some(LoadStatus::Failed(error)) => {
+                                ui.colored_label(egui::Color32::RED, format!("failed ({})", error));
+                            }
+                            None if self.is_loading() => {
+                                ui.label("waiting");
+                            }
+                            None => {
+                                ui.label("not available");
+                            }
+                        }
+                    });
+                }
+            });
+    }
+
+    // Replace a basket's weights (adding it if new) and redraw it
+    fn set_basket(&mut self, basket: Basket) {
+        let asset = basket.asset;
+        self.basket_base_dates.insert(asset, basket.base_date.map(|date| date.to_string()).unwrap_or_default());
+        match self.baskets.iter_mut().find(|b| b.asset == asset) {
+            Some(existing) => *existing = basket,
+            None => self.baskets.push(basket),
+        }
+        self.rebuild_basket(asset);
+    }
+    
+    // Recompute a basket's series from the loaded component prices
+    fn rebuild_basket(&mut self, asset: Asset) {
+        let Some(basket) = self.baskets.iter().find(|b| b.asset == asset) else {
+            return;
+        };
+        if basket.validate().is_err() {
+            return;
+        }
+        match create_consumer_basket(basket, &self.data, self.alignment) {
+            Some(basket_data) => self.data.insert(asset, basket_data),
+            None => self.data.remove(&asset),
+        };
+        self.data_version += 1;
+    }
+    
+    fn show_basket_editor(&mut self, ui: &mut egui::Ui, asset: Asset) {
+        let Some(basket) = self.baskets.iter_mut().find(|b| b.asset == asset) else {
+            return;
+        };
+        let base_date_text = self.basket_base_dates.entry(asset).or_default();
+        let mut changed = false;
+        
+        ui.horizontal(|ui| {
+            ui.label("Base date:");
+            let response = ui.add(egui::TextEdit::singleline(base_date_text)
+                .hint_text("first common date")
+                .desired_width(90.0));
+            if response.changed() {
+                let text = base_date_text.trim();
+                if text.is_empty() {
+                    basket.base_date = None;
+                    changed = true;
+                } else if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+                    basket.base_date = Some(date);
+                    changed = true;
+                }
+            }
+            if basket.base_date.map(|date| date.to_string()) != Some(base_date_text.trim().to_string())
+                && !base_date_text.trim().is_empty() {
+                ui.colored_label(egui::Color32::RED, "use YYYY-MM-DD");
+            }
+        });
+        
+        egui::Grid::new("basket_weights").num_columns(4).show(ui, |ui| {
+            for (i, (asset, weight)) in basket.components.iter_mut().enumerate() {
+                ui.label(asset.base_name());
+                changed |= ui.add(egui::DragValue::new(weight)
+                    .speed(0.1)
+                    .clamp_range(0.0..=WEIGHT_TOTAL)
+                    .suffix("%"))
+                    .changed();
+                if i % 2 == 1 {
+                    ui.end_row();
+                }
+            }
+        });
+        
+        let mut reset = None;
+        ui.horizontal(|ui| {
+            let total = basket.total_weight();
+            match basket.validate() {
+                Ok(()) => ui.label(format!("Total: {:.2}%", total)),
+                Err(_) => ui.colored_label(egui::Color32::RED,
+                    format!("Total: {:.2}%, weights must add up to {}%", total, WEIGHT_TOTAL)),
+            };
+            if ui.button("Scale to 100%").clicked() {
+                basket.normalize_weights();
+                changed = true;
             }
+            if let Some(configured) = asset.basket() {
+                if ui.add_enabled(configured != basket, egui::Button::new("Reset")).clicked() {
+                    reset = Some(configured.clone());
+                }
+            }
+        });
+        
+        if let Some(configured) = reset {
+            self.set_basket(configured);
+        } else if changed {
+            self.rebuild_basket(asset);
+        }
+    }
+    
+    // Prices and rates at the selected frequency; daily data is used as-is
+    fn market_view(&self) -> (Cow<'_, HashMap<Asset, Vec<PriceData>>>, Cow<'_, ExchangeRates>) {
+        if self.frequency == Frequency::Daily {
+            return (Cow::Borrowed(&self.data), Cow::Borrowed(&self.fx));
+        }
+        (Cow::Owned(resample::resample_all(&self.data, self.frequency, self.aggregation)),
+         Cow::Owned(self.fx.resampled(self.frequency, self.aggregation)))
+    }
+    
+    // Write the displayed series to the export directory, returning a status line
+    fn export_chart(&self, format: ExportFormat, from: NaiveDate) -> String {
+        let (data, fx) = self.market_view();
+        let options = ChartOptions { mode: self.representation_mode, alignment: self.alignment, from: Some(from), to: None };
+        let table = series::chart_table(&data, &fx, self.selected_commodity, &self.currencies, &self.compared_baskets, options);
+        let path = export_path(&format!("{}_{}.{}", self.selected_commodity.id(),
+                                        chrono::Local::now().format("%Y-%m-%d_%H%M%S"), format.extension()));
+        match export::export(&table, &path, format) {
+            Ok(()) => format!("Saved {} rows to {}", table.rows.len(), path.display()),
+            Err(e) => {
+                eprintln!("Export failed: {:#}", e);
+                format!("Export failed: {}", e)
+            }
+        }
+    }
+    
+    // Interactive HTML version of the chart, written next to the exports
+    fn export_report(&self, from: NaiveDate, to: NaiveDate) -> String {
+        let path = export_path(&format!("{}_{}.html", self.selected_commodity.id(),
+                                        chrono::Local::now().format("%Y-%m-%d_%H%M%S")));
+        let (data, fx) = self.market_view();
+        let report = HtmlReport::new(&data, &fx, self.selected_commodity)
+            .with_currencies(&self.currencies)
+            .with_compared(&self.compared_baskets)
+            .with_mode(self.representation_mode)
+            .with_alignment(self.alignment)
+            .between(Some(from), Some(to));
+        match report.write(&path) {
+            Ok(()) => format!("Saved report to {}", path.display()),
+            Err(e) => {
+                eprintln!("Report failed: {:#}", e);
+                format!("Report failed: {}", e)
+            }
+        }
+    }
+    
+    // Lines of the % change chart, recomputed only when their inputs change
+    fn chart_series(&mut self) -> Rc<ChartSeries> {
+        let key = SeriesKey {
+            asset: self.selected_commodity,
+            mode: self.representation_mode,
+            alignment: self.alignment,
+            frequency: self.frequency,
+            aggregation: self.aggregation,
+            currencies: self.currencies.clone(),
+            compared: self.compared_baskets.clone(),
+            data_version: self.data_version,
+        };
+        if let Some(series) = self.chart_series.as_ref().filter(|series| series.key == key) {
+            return series.clone();
         }
         
-        points
+        let (data, fx) = self.market_view();
+        let btc = self.calculate_btc_values(&data, self.selected_commodity);
+        let baskets: Vec<(Asset, Vec<[f64; 2]>, Vec<f64>)> = self.compared_baskets.iter()
+            .filter(|basket| **basket != self.selected_commodity)
+            .map(|&basket| (basket, self.calculate_btc_values(&data, basket), self.calculate_carried(&data, basket)))
+            .filter(|(_, points, _)| !points.is_empty())
+            .collect();
+        let currencies: Vec<(Currency, Vec<[f64; 2]>)> = self.currencies.iter()
+            .map(|&currency| (currency, self.calculate_currency_values(&data, &fx, currency)))
+            .filter(|(_, points)| !points.is_empty())
+            .collect();
+        // Bitcoin dates are sorted, so the range is the first and last point
+        let full_range = match (btc.first(), btc.last()) {
+            (Some(first), Some(last)) => [first[0], last[0]],
+            _ => [0.0, 1.0],
+        };
+        let series = Rc::new(ChartSeries {
+            key,
+            btc,
+            btc_carried: self.calculate_carried(&data, self.selected_commodity),
+            usd: self.calculate_usd_values(&data),
+            currencies,
+            baskets,
+            full_range,
+        });
+        self.chart_series = Some(series.clone());
+        series
+    }
+    
+    // Return correlations between every loaded asset and basket within `x_bounds`
+    fn correlations(&mut self, x_bounds: [f64; 2]) -> Rc<CorrelationView> {
+        let key = (self.data_version, self.frequency, self.aggregation, x_bounds);
+        if let Some(view) = self.correlations.as_ref().filter(|view| view.key == key) {
+            return view.clone();
+        }
+        let assets: Vec<Asset> = Asset::tracked().iter().copied().chain(Asset::baskets()).collect();
+        let (from, to) = (Some(plot_x_date(x_bounds[0])), Some(plot_x_date(x_bounds[1])));
+        // Only the range changes while the slider is dragged, so the resampled prices are kept
+        let matrix = if self.frequency == Frequency::Daily {
+            CorrelationMatrix::new(&self.data, &assets, from, to)
+        } else {
+            CorrelationMatrix::new(&self.resampled_data().data, &assets, from, to)
+        };
+        let view = Rc::new(CorrelationView { key, matrix });
+        self.correlations = Some(view.clone());
+        view
+    }
+    
+    // Prices at the chart's (non-daily) frequency, resampled when the data or frequency change
+    fn resampled_data(&mut self) -> Rc<ResampledData> {
+        let key = (self.data_version, self.frequency, self.aggregation);
+        if let Some(resampled) = self.resampled.as_ref().filter(|resampled| resampled.key == key) {
+            return resampled.clone();
+        }
+        let data = resample::resample_all(&self.data, self.frequency, self.aggregation);
+        let resampled = Rc::new(ResampledData { key, data });
+        self.resampled = Some(resampled.clone());
+        resampled
+    }
+    
+    // Write the correlation matrix to the export directory as CSV, returning a status line
+    fn export_correlations(&self, matrix: &CorrelationMatrix) -> String {
+        let path = export_path(&format!("correlations_{}.csv", chrono::Local::now().format("%Y-%m-%d_%H%M%S")));
+        let result = std::fs::create_dir_all(path.parent().unwrap_or(Path::new(".")))
+            .map_err(anyhow::Error::from)
+            .and_then(|_| Ok(std::fs::File::create(&path)?))
+            .and_then(|file| export::write_matrix_csv(file, matrix));
+        match result {
+            Ok(()) => format!("Saved {} assets to {}", matrix.assets.len(), path.display()),
+            Err(e) => {
+                eprintln!("Export failed: {:#}", e);
+                format!("Export failed: {}", e)
+            }
+        }
+    }
+    
+    // DCA lines of the selected asset within `x_bounds`, from daily prices whatever the chart frequency
+    fn dca(&mut self, x_bounds: [f64; 2]) -> Rc<DcaView> {
+        let plan = DcaPlan { from: Some(plot_x_date(x_bounds[0])), to: Some(plot_x_date(x_bounds[1])), ..self.dca_plan };
+        let key = (self.data_version, self.selected_commodity, plan, self.currencies.clone(), self.alignment);
+        if let Some(view) = self.dca.as_ref().filter(|view| view.key == key) {
+            return view.clone();
+        }
+        let lines = dca::simulate(&self.data, &self.fx, self.selected_commodity, &plan, &self.currencies, self.alignment);
+        let view = Rc::new(DcaView { key, lines });
+        self.dca = Some(view.clone());
+        view
+    }
+    
+    // Write the DCA table to the export directory as CSV, returning a status line
+    fn export_dca(&self, lines: &[DcaLine]) -> String {
+        let table = dca::table(lines, self.selected_commodity);
+        let path = export_path(&format!("dca_{}_{}.csv", self.selected_commodity.id(),
+                                        chrono::Local::now().format("%Y-%m-%d_%H%M%S")));
+        match export::export(&table, &path, ExportFormat::Csv) {
+            Ok(()) => format!("Saved {} rows to {}", table.rows.len(), path.display()),
+            Err(e) => {
+                eprintln!("Export failed: {:#}", e);
+                format!("Export failed: {}", e)
+            }
+        }
     }
     
-    fn calculate_usd_values(&self) -> Vec<[f64; 2]> {
-        let commodity_data = match self.data.get(&self.selected_commodity) {
-            Some(data) => data,
-            None => return Vec::new(),
+    // The portfolio valued on every date, from daily prices whatever the chart frequency
+    fn portfolio_history(&mut self) -> Rc<PortfolioView> {
+        let key = (self.data_version, self.portfolio.clone(), self.portfolio_currency, self.selected_commodity, self.alignment);
+        if let Some(view) = self.portfolio_view.as_ref().filter(|view| view.key == key) {
+            return view.clone();
+        }
+        let history = portfolio::value(&self.data, &self.fx, &self.portfolio, self.portfolio_currency,
+                                       self.selected_commodity, self.alignment);
+        let view = Rc::new(PortfolioView { key, history });
+        self.portfolio_view = Some(view.clone());
+        view
+    }
+    
+    // Write the edited holdings to the portfolio file, returning a status line
+    fn save_portfolio(&mut self) -> String {
+        match self.portfolio.save(&self.portfolio_path) {
+            Ok(()) => {
+                self.saved_portfolio = self.portfolio.clone();
+                format!("Saved {} positions to {}", self.portfolio.positions.len(), self.portfolio_path.display())
+            }
+            Err(e) => {
+                eprintln!("Saving portfolio failed: {:#}", e);
+                format!("Saving portfolio failed: {}", e)
+            }
+        }
+    }
+    
+    // Rolling statistics for the chart's inputs, recomputed when they or the window change
+    fn rolling_series(&mut self, key: &SeriesKey) -> Rc<RollingSeries> {
+        if let Some(rolling) = self.rolling_series.as_ref()
+            .filter(|rolling| rolling.key == *key && rolling.window == self.rolling_window) {
+            return rolling.clone();
+        }
+        let (data, _) = self.market_view();
+        let lines = std::iter::once(self.selected_commodity)
+            .chain(self.compared_baskets.iter().copied().filter(|basket| *basket != self.selected_commodity))
+            .filter_map(|asset| {
+                let points = stats::rolling(data.get(&Asset::BITCOIN)?, data.get(&asset)?, self.alignment, self.rolling_window);
+                Some((asset, points))
+            })
+            .collect();
+        let rolling = Rc::new(RollingSeries { key: key.clone(), window: self.rolling_window, lines });
+        self.rolling_series = Some(rolling.clone());
+        rolling
+    }
+    
+    // % change of the lines within `x_bounds`, recomputed when the lines or the bounds change
+    fn chart_changes(&mut self, series: &Rc<ChartSeries>, x_bounds: [f64; 2]) -> Rc<ChartChanges> {
+        if let Some(changes) = self.chart_changes.as_ref()
+            .filter(|changes| Rc::ptr_eq(&changes.series, series) && changes.x_bounds == x_bounds) {
+            return changes.clone();
+        }
+        let changes = Rc::new(ChartChanges::new(series.clone(), x_bounds));
+        self.chart_changes = Some(changes.clone());
+        changes
+    }
+    
+    // Value of `asset` in BTC (or BTC in units of `asset`) on each Bitcoin date
+    fn calculate_btc_values(&self, data: &HashMap<Asset, Vec<PriceData>>, asset: Asset) -> Vec<[f64; 2]> {
+        let (Some(btc_data), Some(commodity_data)) = (data.get(&Asset::BITCOIN), data.get(&asset)) else {
+            return Vec::new();
+        };
+        to_plot_points(series::btc_values(btc_data, commodity_data, self.representation_mode, self.alignment))
+    }
+    
+    // x of the Bitcoin dates `asset` gets a carried price on, when the calendar carries prices
+    fn calculate_carried(&self, data: &HashMap<Asset, Vec<PriceData>>, asset: Asset) -> Vec<f64> {
+        let (Some(btc_data), Some(asset_data)) = (data.get(&Asset::BITCOIN), data.get(&asset)) else {
+            return Vec::new();
         };
+        series::carried_dates(btc_data, asset_data, self.alignment).into_iter()
+            .map(|date| date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp() as f64 / 86400.0)
+            .collect()
+    }
+    
+    fn calculate_usd_values(&self, data: &HashMap<Asset, Vec<PriceData>>) -> Vec<[f64; 2]> {
+        data.get(&self.selected_commodity)
+            .map(|data| to_plot_points(series::usd_values(data, self.representation_mode)))
+            .unwrap_or_default()
+    }
+    
+    fn calculate_currency_values(&self, data: &HashMap<Asset, Vec<PriceData>>, fx: &ExchangeRates, currency: Currency) -> Vec<[f64; 2]> {
+        data.get(&self.selected_commodity)
+            .map(|data| to_plot_points(series::currency_values(data, fx, currency, self.representation_mode)))
+            .unwrap_or_default()
+    }
+}
+
+impl PurchasingPowerApp {
+    // Performance of each line over the visible range, in units of the selected asset
+    fn show_stats(&self, ui: &mut egui::Ui, stats: &[LineStats]) {
+        egui::CollapsingHeader::new("Statistics")
+            .default_open(false)
+            .show(ui, |ui| {
+                if stats.is_empty() {
+                    ui.label("Not enough data in the visible range");
+                    return;
+                }
+                ui.label(format!("What 1 BTC, 1 USD or 1 unit of each currency buys, in {} of {}",
+                                 self.selected_commodity.unit(), self.selected_commodity.base_name()));
+                let pct = |value: Option<f64>| value.map(|v| format!("{:+.1}%", v)).unwrap_or_else(|| "-".to_string());
+                let period = self.frequency.unit();
+                egui::Grid::new("stats_grid").striped(true).spacing([16.0, 4.0]).show(ui, |ui| {
+                    for heading in ["", "Total return", "CAGR", "Volatility", "Max drawdown",
+                                    &format!("Best {}", period), &format!("Worst {}", period), "Sharpe"] {
+                        ui.strong(heading);
+                    }
+                    ui.end_row();
+                    for line in stats {
+                        ui.label(&line.line);
+                        ui.label(pct(Some(line.total_return_pct)));
+                        ui.label(pct(line.cagr_pct));
+                        ui.label(line.volatility_pct.map(|v| format!("{:.1}%", v)).unwrap_or_else(|| "-".to_string()));
+                        ui.label(format!("{:.1}%", line.max_drawdown_pct))
+                            .on_hover_text(format!("{} to {}", line.drawdown_peak, line.drawdown_trough));
+                        ui.label(pct(line.best_period_pct))
+                            .on_hover_text(line.best_period.map(|d| d.to_string()).unwrap_or_default());
+                        ui.label(pct(line.worst_period_pct))
+                            .on_hover_text(line.worst_period.map(|d| d.to_string()).unwrap_or_default());
+                        ui.label(line.sharpe.map(|s| format!("{:.2}", s)).unwrap_or_else(|| "-".to_string()));
+                        ui.end_row();
+                    }
+                });
+                ui.label("Volatility and Sharpe are annualized from the visible points; Sharpe assumes no risk-free rate. Hover a drawdown or period for its dates.");
+            });
+    }
+    
+    // Secondary chart: one rolling statistic of Bitcoin against each drawn asset
+    fn show_rolling_chart(&mut self, ui: &mut egui::Ui, key: &SeriesKey, x_bounds: [f64; 2], plot_height: f32) {
+        egui::CollapsingHeader::new("Rolling correlation and beta")
+            .default_open(false)
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    for metric in RollingMetric::all() {
+                        ui.radio_value(&mut self.rolling_metric, metric, metric.label());
+                    }
+                    ui.separator();
+                    ui.label("Window:");
+                    for window in ROLLING_WINDOWS {
+                        ui.radio_value(&mut self.rolling_window, window, format!("{} days", window));
+                    }
+                });
+                
+                let rolling = self.rolling_series(key);
+                let metric = self.rolling_metric;
+                let lines: Vec<(Asset, Vec<[f64; 2]>)> = rolling.lines.iter()
+                    .map(|(asset, points)| {
+                        let points = points.iter()
+                            .filter_map(|point| {
+                                let x = point.date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp() as f64 / 86400.0;
+                                Some([x, metric.value(point)?]).filter(|_| x >= x_bounds[0] && x <= x_bounds[1])
+                            })
+                            .collect();
+                        (*asset, points)
+                    })
+                    .collect();
+                let values = || lines.iter().flat_map(|(_, points)| points.iter().map(|p| p[1]));
+                let (mut min_y, mut max_y) = match metric {
+                    RollingMetric::Correlation => (-1.0, 1.0),
+                    _ => (values().fold(0.0, f64::min), values().fold(0.0, f64::max)),
+                };
+                let padding = if max_y > min_y { (max_y - min_y) * 0.05 } else { 1.0 };
+                min_y -= padding;
+                max_y += padding;
+                if lines.iter().all(|(_, points)| points.is_empty()) {
+                    ui.label(format!("Not enough aligned prices for a {}-day window in the visible range", self.rolling_window));
+                    return;
+                }
+                
+                Plot::new("rolling_chart")
+                    .height(plot_height)
+                    .x_axis_formatter(|grid_mark, _, _| {
+                        let timestamp = grid_mark.value * 86400.0;
+                        let date = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()
+                            + chrono::Duration::seconds(timestamp as i64);
+                        format!("{}", date.format("%Y-%m"))
+                    })
+                    .y_axis_formatter(move |grid_mark, _, _| match metric {
+                        RollingMetric::RatioVolatility => format!("{:.0}%", grid_mark.value),
+                        _ => format!("{:.2}", grid_mark.value),
+                    })
+                    .legend(Legend::default())
+                    .show_axes([true, true])
+                    .auto_bounds([false, false].into())
+                    .allow_zoom(false)
+                    .allow_drag(false)
+                    .allow_scroll(false)
+                    .show(ui, |plot_ui| {
+                        plot_ui.set_plot_bounds(egui_plot::PlotBounds::from_min_max([x_bounds[0], min_y], [x_bounds[1], max_y]));
+                        for (i, (asset, points)) in lines.into_iter().enumerate() {
+                            // The selected asset in Bitcoin's color, compared baskets like their Bitcoin lines
+                            let (r, g, b) = if i == 0 { BTC_COLOR } else { BASKET_COLORS[(i - 1) % BASKET_COLORS.len()] };
+                            plot_ui.line(Line::new(PlotPoints::from(points))
+                                .name(format!("BTC vs {} ({}-day {})", asset.base_name(), self.rolling_window,
+                                              metric.label().to_lowercase()))
+                                .color(egui::Color32::from_rgb(r, g, b))
+                                .width(if i == 0 { 2.0 } else { 1.5 }));
+                        }
+                    });
+                ui.label("Correlation and beta of Bitcoin's returns against each asset's, and the annualized volatility of their price ratio, over the trailing window");
+            });
+    }
+    
+    // Value over time of the same periodic contributions into BTC, the asset and cash
+    fn show_dca_chart(&mut self, ui: &mut egui::Ui, x_bounds: [f64; 2], plot_height: f32) {
+        let asset = self.selected_commodity;
+        ui.horizontal(|ui| {
+            ui.label("Contribute");
+            ui.add(egui::DragValue::new(&mut self.dca_plan.amount).speed(10.0).clamp_range(1.0..=1_000_000.0));
+            let currencies: Vec<Currency> = std::iter::once(Currency::USD).chain(self.currencies.iter().copied()).collect();
+            if !currencies.contains(&self.dca_plan.currency) {
+                self.dca_plan.currency = Currency::USD;
+            }
+            egui::ComboBox::from_id_source("dca_currency")
+                .selected_text(self.dca_plan.currency.code)
+                .show_ui(ui, |ui| {
+                    for currency in currencies {
+                        ui.selectable_value(&mut self.dca_plan.currency, currency, currency.code);
+                    }
+                });
+            egui::ComboBox::from_id_source("dca_frequency")
+                .selected_text(self.dca_plan.frequency.label())
+                .show_ui(ui, |ui| {
+                    for frequency in Frequency::all() {
+                        ui.selectable_value(&mut self.dca_plan.frequency, frequency, frequency.label());
+                    }
+                });
+            ui.separator();
+            ui.label("Value in:");
+            ui.radio_value(&mut self.dca_in_asset, true, format!("{} of {}", asset.unit(), asset.base_name()));
+            ui.radio_value(&mut self.dca_in_asset, false, self.dca_plan.currency.code);
+        });
         
-        commodity_data.iter().map(|p| {
-            let x = p.date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp() as f64 / 86400.0;
-            let value = match self.representation_mode {
-                RepresentationMode::UnitsPerCurrency => 1.0 / p.price_usd,
-                RepresentationMode::PricePerUnit => p.price_usd,
+        let view = self.dca(x_bounds);
+        if view.lines.is_empty() {
+            ui.label("Not enough prices in the visible range");
+            return;
+        }
+        let currency = self.dca_plan.currency;
+        let in_asset = self.dca_in_asset;
+        let value = |point: &dca::DcaPoint| if in_asset { point.value_in_asset } else { point.value };
+        let x = |date: NaiveDate| date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp() as f64 / 86400.0;
+        let color = |holding: Holding| {
+            let (r, g, b) = match holding {
+                Holding::Bitcoin => BTC_COLOR,
+                Holding::Asset(_) => BASKET_COLORS[2],
+                Holding::Cash(currency) => currency.color,
             };
-            [x, value]
-        }).collect()
+            egui::Color32::from_rgb(r, g, b)
+        };
+        let max_y = view.lines.iter()
+            .flat_map(|line| line.points.iter().map(|p| value(p).max(if in_asset { 0.0 } else { p.contributed })))
+            .fold(0.0, f64::max);
+        
+        Plot::new("dca_chart")
+            .height(plot_height * 0.8)
+            .x_axis_formatter(|grid_mark, _, _| {
+                let timestamp = grid_mark.value * 86400.0;
+                let date = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()
+                    + chrono::Duration::seconds(timestamp as i64);
+                format!("{}", date.format("%Y-%m"))
+            })
+            .legend(Legend::default())
+            .show_axes([true, true])
+            .auto_bounds([false, false].into())
+            .allow_zoom(false)
+            .allow_drag(false)
+            .allow_scroll(false)
+            .show(ui, |plot_ui| {
+                plot_ui.set_plot_bounds(egui_plot::PlotBounds::from_min_max([x_bounds[0], 0.0], [x_bounds[1], max_y * 1.05]));
+                if !in_asset {
+                    let points: Vec<[f64; 2]> = view.lines[0].points.iter().map(|p| [x(p.date), p.contributed]).collect();
+                    plot_ui.line(Line::new(PlotPoints::from(points))
+                        .name(format!("Contributed ({})", currency.code))
+                        .color(egui::Color32::GRAY)
+                        .style(egui_plot::LineStyle::dashed_dense()));
+                }
+                for line in &view.lines {
+                    let points: Vec<[f64; 2]> = line.points.iter().map(|p| [x(p.date), value(p)]).collect();
+                    let unit = if in_asset { asset.unit() } else { currency.code };
+                    plot_ui.line(Line::new(PlotPoints::from(points))
+                        .name(format!("{} ({})", line.holding.label(), unit))
+                        .color(color(line.holding))
+                        .width(if line.holding == Holding::Bitcoin { 2.5 } else { 1.5 }));
+                }
+            });
+        
+        ui.horizontal(|ui| {
+            let contributed = view.lines[0].last().map(|p| p.contributed).unwrap_or(0.0);
+            ui.label(format!("{:.2} {} contributed {} from {} to {}", contributed, currency.code,
+                             self.dca_plan.frequency.label().to_lowercase(),
+                             plot_x_date(x_bounds[0]).format("%Y-%m-%d"), plot_x_date(x_bounds[1]).format("%Y-%m-%d")));
+            if ui.button("Export CSV").clicked() {
+                self.export_status = Some(self.export_dca(&view.lines));
+            }
+        });
+        egui::Grid::new("dca_grid").striped(true).spacing([16.0, 4.0]).show(ui, |ui| {
+            for heading in ["", "Held", &format!("Value ({})", currency.code), "Return",
+                            &format!("Value ({} of {})", asset.unit(), asset.base_name())] {
+                ui.strong(heading);
+            }
+            ui.end_row();
+            for line in &view.lines {
+                let Some(last) = line.last() else {
+                    continue;
+                };
+                ui.label(line.holding.label());
+                ui.label(format!("{:.6} {}", last.held, line.holding.unit()));
+                ui.label(format!("{:.2}", last.value));
+                ui.label(line.return_pct().map(|r| format!("{:+.1}%", r)).unwrap_or_else(|| "-".to_string()));
+                ui.label(format!("{:.4}", last.value_in_asset));
+                ui.end_row();
+            }
+        });
+    }
+    
+    // The saved holdings valued over time, with an editor for the positions
+    fn show_portfolio(&mut self, ui: &mut egui::Ui, x_bounds: [f64; 2], plot_height: f32) {
+        let asset = self.selected_commodity;
+        ui.horizontal(|ui| {
+            ui.label("Value in:");
+            ui.radio_value(&mut self.portfolio_in_asset, true, format!("{} of {}", asset.unit(), asset.base_name()));
+            ui.radio_value(&mut self.portfolio_in_asset, false, "Currency");
+            let currencies: Vec<Currency> = std::iter::once(Currency::USD).chain(self.currencies.iter().copied()).collect();
+            if !currencies.contains(&self.portfolio_currency) {
+                self.portfolio_currency = Currency::USD;
+            }
+            egui::ComboBox::from_id_source("portfolio_currency")
+                .selected_text(self.portfolio_currency.code)
+                .show_ui(ui, |ui| {
+                    for currency in currencies {
+                        ui.selectable_value(&mut self.portfolio_currency, currency, currency.code);
+                    }
+                });
+        });
+        
+        let view = self.portfolio_history();
+        let history = &view.history;
+        let currency = self.portfolio_currency;
+        let in_asset = self.portfolio_in_asset;
+        let unit = if in_asset { asset.unit() } else { currency.code };
+        // USD amounts in the displayed unit on a valuation's date
+        let scale = |point: &portfolio::Valuation, usd: f64| if in_asset { usd / point.asset_usd } else { usd * point.rate };
+        let x = |date: NaiveDate| date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp() as f64 / 86400.0;
+        let visible: Vec<&portfolio::Valuation> = history.points.iter()
+            .filter(|p| x(p.date) >= x_bounds[0] && x(p.date) <= x_bounds[1])
+            .collect();
+        
+        if visible.is_empty() {
+            ui.label(if self.portfolio.positions.is_empty() {
+                "Add positions below to value them over time"
+            } else {
+                "No prices for the holdings in the visible range"
+            });
+        } else {
+            let max_y = visible.iter().map(|p| scale(p, p.total_usd())).fold(0.0, f64::max);
+            Plot::new("portfolio_chart")
+                .height(plot_height * 0.6)
+                .x_axis_formatter(|grid_mark, _, _| {
+                    let timestamp = grid_mark.value * 86400.0;
+                    let date = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()
+                        + chrono::Duration::seconds(timestamp as i64);
+                    format!("{}", date.format("%Y-%m"))
+                })
+                .legend(Legend::default())
+                .show_axes([true, true])
+                .auto_bounds([false, false].into())
+                .allow_zoom(false)
+                .allow_drag(false)
+                .allow_scroll(false)
+                .show(ui, |plot_ui| {
+                    plot_ui.set_plot_bounds(egui_plot::PlotBounds::from_min_max([x_bounds[0], 0.0], [x_bounds[1], max_y * 1.05]));
+                    let total: Vec<[f64; 2]> = visible.iter().map(|p| [x(p.date), scale(p, p.total_usd())]).collect();
+                    plot_ui.line(Line::new(PlotPoints::from(total))
+                        .name(format!("Total ({})", unit))
+                        .color(plot_ui.ctx().style().visuals.text_color())
+                        .width(2.5));
+                    if history.instruments.len() > 1 {
+                        for (i, instrument) in history.instruments.iter().enumerate() {
+                            let (r, g, b) = match instrument {
+                                Instrument::Asset(held) if *held == Asset::BITCOIN => BTC_COLOR,
+                                Instrument::Asset(_) => BASKET_COLORS[i % BASKET_COLORS.len()],
+                                Instrument::Cash(cash) => cash.color,
+                            };
+                            let points: Vec<[f64; 2]> = visible.iter().map(|p| [x(p.date), scale(p, p.parts_usd[i])]).collect();
+                            plot_ui.line(Line::new(PlotPoints::from(points))
+                                .name(format!("{} ({})", instrument.label(), unit))
+                                .color(egui::Color32::from_rgb(r, g, b))
+                                .width(1.5));
+                        }
+                    }
+                });
+            if let (Some(first), Some(last)) = (visible.first(), visible.last()) {
+                let (start, end) = (scale(first, first.total_usd()), scale(last, last.total_usd()));
+                ui.label(format!("{:.4} {} on {}, {:+.1}% since {}", end, unit, last.date,
+                                 if start > 0.0 { (end / start - 1.0) * 100.0 } else { 0.0 }, first.date))
+                    .on_hover_text("The change includes positions added in the visible range");
+            }
+        }
+        
+        let mut remove = None;
+        egui::CollapsingHeader::new("Positions")
+            .default_open(true)
+            .show(ui, |ui| {
+                egui::Grid::new("portfolio_positions").striped(true).num_columns(4).show(ui, |ui| {
+                    for heading in ["Holding", "Amount", "Date", ""] {
+                        ui.strong(heading);
+                    }
+                    ui.end_row();
+                    for (i, (position, date_text)) in self.portfolio.positions.iter_mut().zip(&mut self.portfolio_dates).enumerate() {
+                        egui::ComboBox::from_id_source(("portfolio_instrument", i))
+                            .selected_text(position.instrument.label())
+                            .show_ui(ui, |ui| {
+                                for held in Asset::tracked() {
+                                    ui.selectable_value(&mut position.instrument, Instrument::Asset(*held), held.base_name());
+                                }
+                                ui.separator();
+                                for cash in Currency::all() {
+                                    let instrument = Instrument::Cash(*cash);
+                                    ui.selectable_value(&mut position.instrument, instrument, instrument.label());
+                                }
+                            });
+                        ui.add(egui::DragValue::new(&mut position.amount).speed(0.01).max_decimals(8)
+                            .suffix(format!(" {}", position.instrument.unit())));
+                        ui.horizontal(|ui| {
+                            if ui.add(egui::TextEdit::singleline(date_text).desired_width(90.0)).changed() {
+                                if let Ok(date) = NaiveDate::parse_from_str(date_text.trim(), "%Y-%m-%d") {
+                                    position.date = date;
+                                }
+                            }
+                            if position.date.to_string() != date_text.trim() {
+                                ui.colored_label(egui::Color32::RED, "use YYYY-MM-DD");
+                            }
+                        });
+                        if ui.small_button("✖").on_hover_text("Remove position").clicked() {
+                            remove = Some(i);
+                        }
+                        ui.end_row();
+                    }
+                });
+                
+                ui.horizontal(|ui| {
+                    if ui.button("Add position").clicked() {
+                        let date = chrono::Local::now().date_naive();
+                        self.portfolio.positions.push(Position { instrument: Instrument::Asset(Asset::BITCOIN), amount: 0.0, date });
+                        self.portfolio_dates.push(date.to_string());
+                    }
+                    let unsaved = self.portfolio != self.saved_portfolio;
+                    if ui.add_enabled(unsaved, egui::Button::new("Save")).clicked() {
+                        self.export_status = Some(self.save_portfolio());
+                    }
+                    if ui.add_enabled(unsaved, egui::Button::new("Revert")).clicked() {
+                        self.portfolio = self.saved_portfolio.clone();
+                        self.portfolio_dates = self.portfolio.positions.iter().map(|p| p.date.to_string()).collect();
+                    }
+                    if unsaved {
+                        ui.label(format!("Unsaved changes to {}", self.portfolio_path.display()));
+                    }
+                });
+            });
+        if let Some(i) = remove {
+            self.portfolio.positions.remove(i);
+            self.portfolio_dates.remove(i);
+        }
+        
+        // Cash in a currency that isn't loaded yet needs its exchange rates
+        let missing: Vec<Currency> = self.portfolio.positions.iter()
+            .filter_map(|p| match p.instrument {
+                Instrument::Cash(cash) if !self.fx.has(cash) && !self.currencies.contains(&cash) => Some(cash),
+                _ => None,
+            })
+            .collect();
+        if !missing.is_empty() {
+            for currency in missing {
+                if !self.currencies.contains(&currency) {
+                    self.currencies.push(currency);
+                }
+            }
+            let ctx = ui.ctx().clone();
+            self.refresh(&ctx);
+        }
     }
     
-    fn calculate_cad_values(&self) -> Vec<[f64; 2]> {
-        let commodity_data = match self.data.get(&self.selected_commodity) {
-            Some(data) => data,
-            None => return Vec::new(),
+    // Heatmap of return correlations, red for +1 through white to blue for -1
+    fn show_correlation_matrix(&mut self, ui: &mut egui::Ui, x_bounds: [f64; 2], plot_height: f32) {
+        let view = self.correlations(x_bounds);
+        let matrix = &view.matrix;
+        ui.horizontal(|ui| {
+            ui.label(format!("{} returns from {} to {}", self.frequency.label(),
+                             plot_x_date(x_bounds[0]).format("%Y-%m-%d"), plot_x_date(x_bounds[1]).format("%Y-%m-%d")));
+            if ui.add_enabled(!matrix.assets.is_empty(), egui::Button::new("Export CSV")).clicked() {
+                self.export_status = Some(self.export_correlations(matrix));
+            }
+        });
+        let n = matrix.assets.len();
+        if n == 0 {
+            ui.label("Not enough prices in the visible range");
+            return;
+        }
+        
+        let font = egui::FontId::proportional(12.0);
+        let text_color = ui.visuals().text_color();
+        let label_width = matrix.assets.iter()
+            .map(|asset| ui.painter().layout_no_wrap(asset.base_name().to_string(), font.clone(), text_color).size().x)
+            .fold(0.0, f32::max) + 8.0;
+        let grid_size = (ui.available_width() - label_width).min(plot_height - label_width).max(n as f32 * 12.0);
+        let cell = grid_size / n as f32;
+        let (response, painter) = ui.allocate_painter(egui::vec2(label_width + grid_size, label_width + grid_size), egui::Sense::hover());
+        let origin = response.rect.min + egui::vec2(label_width, label_width);
+        
+        let color = |value: f64| {
+            let t = value.abs().min(1.0) as f32;
+            let fade = |channel: u8| (255.0 + (channel as f32 - 255.0) * t) as u8;
+            if value >= 0.0 {
+                egui::Color32::from_rgb(fade(215), fade(48), fade(39))
+            } else {
+                egui::Color32::from_rgb(fade(49), fade(54), fade(149))
+            }
         };
+        for (i, asset) in matrix.assets.iter().enumerate() {
+            let offset = i as f32 * cell;
+            // Row labels on the left, column labels rotated above
+            painter.text(egui::pos2(origin.x - 4.0, origin.y + offset + cell / 2.0), egui::Align2::RIGHT_CENTER,
+                         asset.base_name(), font.clone(), text_color);
+            let galley = painter.layout_no_wrap(asset.base_name().to_string(), font.clone(), text_color);
+            let pos = egui::pos2(origin.x + offset + (cell - galley.size().y) / 2.0, origin.y - 4.0);
+            painter.add(egui::epaint::TextShape::new(pos, galley, text_color).with_angle(-std::f32::consts::FRAC_PI_2));
+            
+            for (j, value) in matrix.values[i].iter().enumerate() {
+                let rect = egui::Rect::from_min_size(origin + egui::vec2(j as f32 * cell, offset), egui::vec2(cell, cell)).shrink(0.5);
+                match value {
+                    Some(value) => {
+                        painter.rect_filled(rect, 0.0, color(*value));
+                        if cell >= 30.0 {
+                            let ink = if value.abs() > 0.6 { egui::Color32::WHITE } else { egui::Color32::BLACK };
+                            painter.text(rect.center(), egui::Align2::CENTER_CENTER, format!("{:.2}", value),
+                                         egui::FontId::proportional((cell * 0.3).min(13.0)), ink);
+                        }
+                    }
+                    None => {
+                        painter.rect_stroke(rect, 0.0, egui::Stroke::new(1.0, egui::Color32::LIGHT_GRAY));
+                    }
+                }
+            }
+        }
         
-        commodity_data.iter().filter_map(|p| {
-            p.price_cad.map(|cad_price| {
-                let x = p.date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp() as f64 / 86400.0;
-                let value = match self.representation_mode {
-                    RepresentationMode::UnitsPerCurrency => 1.0 / cad_price,
-                    RepresentationMode::PricePerUnit => cad_price,
+        // Hovered cell: the pair, its correlation and how many returns it came from
+        if let Some(pointer) = response.hover_pos() {
+            let (col, row) = (((pointer.x - origin.x) / cell).floor(), ((pointer.y - origin.y) / cell).floor());
+            if col >= 0.0 && row >= 0.0 && (col as usize) < n && (row as usize) < n {
+                let (i, j) = (row as usize, col as usize);
+                let text = match matrix.values[i][j] {
+                    Some(value) => format!("{} vs {}: {:.3} from {} returns", matrix.assets[i].base_name(),
+                                           matrix.assets[j].base_name(), value, matrix.counts[i][j]),
+                    None => format!("{} vs {}: not enough common prices", matrix.assets[i].base_name(), matrix.assets[j].base_name()),
                 };
-                [x, value]
+                response.on_hover_text_at_pointer(text);
+            }
+        }
+    }
+    
+    fn show_candlestick_chart(&self, ui: &mut egui::Ui, x_bounds: [f64; 2], plot_height: f32) {
+        let asset = if self.candles_for_bitcoin { Asset::BITCOIN } else { self.selected_commodity };
+        let bars: Vec<(f64, NaiveDate, Ohlcv)> = self.data.get(&asset)
+            .map(|data| resample::resample(data, self.frequency, Aggregation::Ohlc).into_iter()
+                .filter_map(|p| {
+                    let x = p.date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp() as f64 / 86400.0;
+                    p.ohlcv.filter(|_| x >= x_bounds[0] && x <= x_bounds[1]).map(|bar| (x, p.date, bar))
+                })
+                .collect())
+            .unwrap_or_default();
+        
+        if bars.is_empty() {
+            ui.label(format!("No open/high/low/close bars available for {} (its source only provides closing prices)", asset.base_name()));
+            return;
+        }
+        
+        // Extremes and average true range over the visible range
+        let (high_date, high) = bars.iter()
+            .map(|(_, date, bar)| (*date, bar.high))
+            .fold((bars[0].1, f64::NEG_INFINITY), |best, (date, high)| if high > best.1 { (date, high) } else { best });
+        let (low_date, low) = bars.iter()
+            .map(|(_, date, bar)| (*date, bar.low))
+            .fold((bars[0].1, f64::INFINITY), |best, (date, low)| if low < best.1 { (date, low) } else { best });
+        let atr_window = bars.len().min(14);
+        let recent = &bars[bars.len() - atr_window..];
+        let atr = recent.iter().enumerate()
+            .map(|(i, (_, _, bar))| {
+                let prev_close = if i > 0 { Some(recent[i - 1].2.close) } else { None };
+                bar.true_range(prev_close)
+            })
+            .sum::<f64>() / atr_window as f64;
+        let last_close = bars[bars.len() - 1].2.close;
+        
+        ui.horizontal(|ui| {
+            ui.label(format!("High: ${:.2} on {}", high, high_date.format("%Y-%m-%d")));
+            ui.label(format!("Low: ${:.2} on {}", low, low_date.format("%Y-%m-%d")));
+            ui.label(format!("{}-{} ATR: ${:.2} ({:.1}% of close)", atr_window, self.frequency.unit(), atr, atr / last_close * 100.0));
+        });
+        
+        let up_color = egui::Color32::from_rgb(0, 170, 90);
+        let down_color = egui::Color32::from_rgb(220, 50, 50);
+        let candles: Vec<BoxElem> = bars.iter()
+            .map(|(x, _, bar)| {
+                let color = if bar.close >= bar.open { up_color } else { down_color };
+                let body_low = bar.open.min(bar.close);
+                let body_high = bar.open.max(bar.close);
+                BoxElem::new(*x, BoxSpread::new(bar.low, body_low, (body_low + body_high) / 2.0, body_high, bar.high))
+                    .box_width(0.8 * self.frequency.days())
+                    .whisker_width(0.0)
+                    .fill(color)
+                    .stroke(egui::Stroke::new(1.0, color))
+            })
+            .collect();
+        
+        let padding = ((high - low) * 0.05).max(high.abs() * 0.01);
+        
+        Plot::new("candlestick_chart")
+            .height(plot_height)
+            .x_axis_formatter(|grid_mark, _, _| {
+                let timestamp = grid_mark.value * 86400.0;
+                let date = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()
+                    + chrono::Duration::seconds(timestamp as i64);
+                format!("{}", date.format("%Y-%m"))
             })
-        }).collect()
+            .y_axis_formatter(|grid_mark, _, _| {
+                format!("${:.2}", grid_mark.value)
+            })
+            .legend(Legend::default())
+            .show_axes([true, true])
+            .auto_bounds([false, false].into())
+            .allow_zoom(false)
+            .allow_drag(false)
+            .allow_scroll(false)
+            .show(ui, |plot_ui| {
+                plot_ui.set_plot_bounds(egui_plot::PlotBounds::from_min_max(
+                    [x_bounds[0], low - padding],
+                    [x_bounds[1], high + padding]
+                ));
+                plot_ui.box_plot(BoxPlot::new(candles).name(format!("{} (USD)", asset.name())));
+            });
     }
 }
 
@@ -133,16 +1395,41 @@ impl eframe::App for PurchasingPowerApp {
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("Bitcoin Purchasing Power vs Real Assets");
             
+            self.poll_loader(ctx);
+            self.check_auto_refresh(ctx);
+            self.show_load_status(ui);
+            
+            ui.horizontal(|ui| {
+                if ui.add_enabled(!self.is_loading(), egui::Button::new("🔄 Refresh")).clicked() {
+                    self.refresh(ctx);
+                }
+                
+                ui.label("Auto-refresh:");
+                let selected_label = AUTO_REFRESH_OPTIONS.iter()
+                    .find(|(interval, _)| *interval == self.auto_refresh)
+                    .map(|(_, label)| *label)
+                    .unwrap_or("Off");
+                egui::ComboBox::from_id_source("auto_refresh")
+                    .selected_text(selected_label)
+                    .show_ui(ui, |ui| {
+                        for (interval, label) in AUTO_REFRESH_OPTIONS {
+                            ui.selectable_value(&mut self.auto_refresh, interval, label);
+                        }
+                    });
+            });
+            
             // Check if we have data
             if self.data.is_empty() {
-                ui.separator();
-                ui.colored_label(egui::Color32::RED, "Failed to fetch data from Yahoo Finance!");
-                ui.label("Please check your internet connection and restart the application.");
+                if !self.is_loading() {
+                    ui.separator();
+                    ui.colored_label(egui::Color32::RED, "Failed to fetch data from Yahoo Finance!");
+                    ui.label("Please check your internet connection and restart the application.");
+                }
                 return;
             }
             
             // Check if we have Bitcoin data specifically
-            if !self.data.contains_key(&Asset::Bitcoin) {
+            if !self.is_loading() && !self.data.contains_key(&Asset::BITCOIN) {
                 ui.separator();
                 ui.colored_label(egui::Color32::YELLOW, "Warning: Bitcoin data not available");
                 ui.label("The chart will not display properly without Bitcoin price data.");
@@ -153,49 +1440,150 @@ impl eframe::App for PurchasingPowerApp {
                 egui::ComboBox::from_label("Asset")
                     .selected_text(self.selected_commodity.name())
                     .show_ui(ui, |ui| {
-                        ui.selectable_value(&mut self.selected_commodity, Asset::ConsumerBasket, "🛒 Consumer Basket (Blended)");
-                        ui.separator();
-                        ui.selectable_value(&mut self.selected_commodity, Asset::Gold, "Gold (per oz)");
-                        ui.selectable_value(&mut self.selected_commodity, Asset::Silver, "Silver (per oz)");
-                        ui.selectable_value(&mut self.selected_commodity, Asset::Oil, "Crude Oil (per barrel)");
-                        ui.selectable_value(&mut self.selected_commodity, Asset::NaturalGas, "Natural Gas (per MMBtu)");
-                        ui.selectable_value(&mut self.selected_commodity, Asset::Copper, "Copper (per lb)");
-                        ui.separator();
-                        ui.selectable_value(&mut self.selected_commodity, Asset::Wheat, "Wheat (per bushel)");
-                        ui.selectable_value(&mut self.selected_commodity, Asset::Corn, "Corn (per bushel)");
-                        ui.selectable_value(&mut self.selected_commodity, Asset::Soybeans, "Soybeans (per bushel)");
-                        ui.selectable_value(&mut self.selected_commodity, Asset::Rice, "Rice (per cwt)");
-                        ui.separator();
-                        ui.selectable_value(&mut self.selected_commodity, Asset::Beef, "Beef (per pound)");
-                        ui.selectable_value(&mut self.selected_commodity, Asset::Coffee, "Coffee (per lb)");
-                        ui.selectable_value(&mut self.selected_commodity, Asset::Sugar, "Sugar (per lb)");
-                        ui.selectable_value(&mut self.selected_commodity, Asset::Cotton, "Cotton (per lb)");
-                        ui.selectable_value(&mut self.selected_commodity, Asset::Lumber, "Lumber (per 1000 bd ft)");
+                        for basket in Asset::baskets() {
+                            ui.selectable_value(&mut self.selected_commodity, basket, format!("🛒 {}", basket.name()));
+                        }
+                        // Assets from the registry, grouped by category in config order
+                        let mut category = None;
+                        for &asset in Asset::tracked().iter().filter(|a| **a != Asset::BITCOIN) {
+                            if category != Some(asset.category()) {
+                                ui.separator();
+                                category = Some(asset.category());
+                            }
+                            ui.selectable_value(&mut self.selected_commodity, asset, asset.name());
+                        }
                     });
             });
             
+            if self.selected_commodity.is_basket() {
+                let asset = self.selected_commodity;
+                egui::CollapsingHeader::new(format!("Basket: {}", asset.name()))
+                    .show(ui, |ui| self.show_basket_editor(ui, asset));
+            }
+            
+            // Other baskets can be drawn as extra Bitcoin lines on the same chart
+            if Asset::baskets().nth(1).is_some() {
+                ui.horizontal(|ui| {
+                    ui.label("Also compare baskets:");
+                    for basket in Asset::baskets().filter(|b| *b != self.selected_commodity) {
+                        let mut enabled = self.compared_baskets.contains(&basket);
+                        if ui.checkbox(&mut enabled, basket.name()).changed() {
+                            if enabled {
+                                self.compared_baskets.push(basket);
+                            } else {
+                                self.compared_baskets.retain(|b| *b != basket);
+                            }
+                        }
+                    }
+                });
+            }
+            
             ui.horizontal(|ui| {
                 ui.label("Display mode:");
                 ui.radio_value(&mut self.representation_mode, RepresentationMode::UnitsPerCurrency, "Units per Currency");
                 ui.radio_value(&mut self.representation_mode, RepresentationMode::PricePerUnit, "Price per Unit");
             });
+            
+            ui.horizontal(|ui| {
+                ui.label("Frequency:");
+                egui::ComboBox::from_id_source("frequency")
+                    .selected_text(self.frequency.label())
+                    .show_ui(ui, |ui| {
+                        for frequency in Frequency::all() {
+                            ui.selectable_value(&mut self.frequency, frequency, frequency.label());
+                        }
+                    })
+                    .response
+                    .on_hover_text("Resample the daily prices, one point per period dated on its first day");
+                if self.frequency != Frequency::Daily {
+                    egui::ComboBox::from_id_source("aggregation")
+                        .selected_text(self.aggregation.label())
+                        .show_ui(ui, |ui| {
+                            for aggregation in Aggregation::all() {
+                                ui.selectable_value(&mut self.aggregation, aggregation, aggregation.label());
+                            }
+                        })
+                        .response
+                        .on_hover_text("Value of each period in the % change chart; candlesticks always combine the daily bars");
+                }
+            });
+            
+            ui.horizontal(|ui| {
+                ui.label("Date matching:");
+                let before = self.alignment;
+                egui::ComboBox::from_id_source("alignment")
+                    .selected_text(self.alignment.policy.label())
+                    .show_ui(ui, |ui| {
+                        for policy in AlignPolicy::all() {
+                            ui.selectable_value(&mut self.alignment.policy, policy, policy.label());
+                        }
+                    })
+                    .response
+                    .on_hover_text("How an asset gets a price on days it has none, e.g. weekends and holidays");
+                if self.alignment.policy != AlignPolicy::Exact {
+                    ui.label("within");
+                    ui.add(egui::DragValue::new(&mut self.alignment.max_days).clamp_range(1..=365).suffix(" days"));
+                }
+                ui.label("Calendar:");
+                egui::ComboBox::from_id_source("calendar")
+                    .selected_text(self.alignment.calendar.label())
+                    .show_ui(ui, |ui| {
+                        for calendar in Calendar::all() {
+                            ui.selectable_value(&mut self.alignment.calendar, calendar, calendar.label());
+                        }
+                    })
+                    .response
+                    .on_hover_text("Days the asset is compared with Bitcoin on, which trades every day");
+                // Baskets combine components priced on different days; the calendar only
+                // applies when comparing with Bitcoin
+                if (self.alignment.policy, self.alignment.max_days) != (before.policy, before.max_days) {
+                    let baskets: Vec<Asset> = self.baskets.iter().map(|b| b.asset).collect();
+                    for basket in baskets {
+                        self.rebuild_basket(basket);
+                    }
+                }
+            });
+            
+            ui.horizontal(|ui| {
+                ui.label("Currencies:");
+                let mut changed = false;
+                for currency in Currency::all().iter().filter(|c| **c != Currency::USD) {
+                    let mut enabled = self.currencies.contains(currency);
+                    let response = ui.add_enabled(!self.is_loading(), egui::Checkbox::new(&mut enabled, currency.code))
+                        .on_hover_text(currency.name);
+                    if response.changed() {
+                        if enabled {
+                            self.currencies.push(*currency);
+                        } else {
+                            self.currencies.retain(|c| c != currency);
+                        }
+                        changed = true;
+                    }
+                }
+                // Newly enabled currencies need their exchange rates fetched
+                if changed && self.currencies.iter().any(|c| !self.fx.has(*c)) {
+                    self.refresh(ctx);
+                }
+            });
+            
+            ui.horizontal(|ui| {
+                ui.label("Chart:");
+                ui.radio_value(&mut self.chart_style, ChartStyle::PercentChange, "% change");
+                ui.radio_value(&mut self.chart_style, ChartStyle::Candlesticks, "Candlesticks");
+                ui.radio_value(&mut self.chart_style, ChartStyle::Correlations, "Correlations");
+                ui.radio_value(&mut self.chart_style, ChartStyle::Dca, "DCA");
+                ui.radio_value(&mut self.chart_style, ChartStyle::Portfolio, "Portfolio");
+                if self.chart_style == ChartStyle::Candlesticks {
+                    ui.checkbox(&mut self.candles_for_bitcoin, "Show Bitcoin");
+                }
+            });
 
             ui.separator();
 
             let plot_height = ui.available_height() * 0.85;
-            // Calculate data bounds for x-axis range
-            let btc_points = self.calculate_btc_values();
-            let usd_points = self.calculate_usd_values();
-            let cad_points = self.calculate_cad_values();
-            
-            // Get the full data range
-            let full_range = if !btc_points.is_empty() {
-                let min_x = btc_points.iter().map(|p| p[0]).fold(f64::INFINITY, f64::min);
-                let max_x = btc_points.iter().map(|p| p[0]).fold(f64::NEG_INFINITY, f64::max);
-                [min_x, max_x]
-            } else {
-                [0.0, 1.0]
-            };
+            let series = self.chart_series();
+            let btc_points = &series.btc;
+            let full_range = series.full_range;
             
             // Slider controls the start date (0.0 = earliest data, 1.0 = most recent data)
             let total_range = full_range[1] - full_range[0];
@@ -240,148 +1628,197 @@ impl eframe::App for PurchasingPowerApp {
                 }
             });
             
+            // Export the numbers behind the chart from the slider start on
+            ui.horizontal(|ui| {
+                ui.label("Export:");
+                let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+                let from = epoch + chrono::Duration::days(x_bounds[0].ceil() as i64);
+                for format in ExportFormat::all() {
+                    let button = egui::Button::new(format.extension().to_uppercase());
+                    if ui.add_enabled(!btc_points.is_empty(), button).clicked() {
+                        self.export_status = Some(self.export_chart(format, from));
+                    }
+                }
+                if ui.add_enabled(!btc_points.is_empty(), egui::Button::new("HTML report")).clicked() {
+                    let to = epoch + chrono::Duration::days(x_bounds[1].floor() as i64);
+                    self.export_status = Some(self.export_report(from, to));
+                }
+                if let Some(status) = &self.export_status {
+                    ui.label(status);
+                }
+            });
+            
+            // Show which source each range of prices came from
+            let btc_sources = self.data.get(&Asset::BITCOIN).map(|d| source_ranges(d)).unwrap_or_default();
+            for (asset, ranges) in [
+                (Asset::BITCOIN, btc_sources.clone()),
+                (self.selected_commodity, self.data.get(&self.selected_commodity).map(|d| source_ranges(d)).unwrap_or_default()),
+            ] {
+                if ranges.is_empty() {
+                    continue;
+                }
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} data from:", asset.base_name()));
+                    for (source, from, to) in &ranges {
+                        ui.label(format!("{} ({} to {})",
+                                         source.unwrap_or("derived"),
+                                         from.format("%Y-%m-%d"),
+                                         to.format("%Y-%m-%d")));
+                    }
+                });
+            }
+            
             ui.separator();
             
-            // Combined chart with two Y-axes
-            Plot::new("combined_chart")
-                .height(plot_height)
-                .x_axis_formatter(|grid_mark, _, _| {
-                    let timestamp = grid_mark.value * 86400.0;
-                    let date = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()
-                        + chrono::Duration::seconds(timestamp as i64);
-                    format!("{}", date.format("%Y-%m"))
-                })
-                .y_axis_formatter(|grid_mark, _, _| {
-                    format!("{:.1}%", grid_mark.value)
-                })
-                .legend(Legend::default())
-                .show_axes([true, true])
-                .auto_bounds([false, false].into())
-                .allow_zoom(false)
-                .allow_drag(false)
-                .allow_scroll(false)
-                .show(ui, |plot_ui| {
-                    // Find the first point in the visible range for base values
-                    let btc_base_idx = btc_points.iter().position(|p| p[0] >= x_bounds[0]).unwrap_or(0);
-                    let usd_base_idx = usd_points.iter().position(|p| p[0] >= x_bounds[0]).unwrap_or(0);
-                    let cad_base_idx = cad_points.iter().position(|p| p[0] >= x_bounds[0]).unwrap_or(0);
-                    
-                    // Always show all lines as percentage change from start
-                    if !btc_points.is_empty() && !usd_points.is_empty() {
-                        let btc_base = btc_points.get(btc_base_idx).map(|p| p[1]).unwrap_or(1.0);
-                        let usd_base = usd_points.get(usd_base_idx).map(|p| p[1]).unwrap_or(1.0);
-                        
-                        // Convert to percentage change
-                        let btc_pct: Vec<[f64; 2]> = btc_points.iter()
-                            .skip(btc_base_idx)
-                            .filter(|p| p[0] >= x_bounds[0] && p[0] <= x_bounds[1])
-                            .map(|p| [p[0], ((p[1] / btc_base) - 1.0) * 100.0])
-                            .collect();
+            if self.chart_style == ChartStyle::Candlesticks {
+                self.show_candlestick_chart(ui, x_bounds, plot_height);
+            } else if self.chart_style == ChartStyle::Correlations {
+                self.show_correlation_matrix(ui, x_bounds, plot_height);
+            } else if self.chart_style == ChartStyle::Dca {
+                self.show_dca_chart(ui, x_bounds, plot_height);
+            } else if self.chart_style == ChartStyle::Portfolio {
+                self.show_portfolio(ui, x_bounds, plot_height);
+            } else {
+                let changes = self.chart_changes(&series, x_bounds);
+                // Combined chart with two Y-axes
+                Plot::new("combined_chart")
+                    .height(plot_height)
+                    .x_axis_formatter(|grid_mark, _, _| {
+                        let timestamp = grid_mark.value * 86400.0;
+                        let date = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()
+                            + chrono::Duration::seconds(timestamp as i64);
+                        format!("{}", date.format("%Y-%m"))
+                    })
+                    .y_axis_formatter(|grid_mark, _, _| {
+                        format!("{:.1}%", grid_mark.value)
+                    })
+                    .legend(Legend::default())
+                    .show_axes([true, true])
+                    .auto_bounds([false, false].into())
+                    .allow_zoom(false)
+                    .allow_drag(false)
+                    .allow_scroll(false)
+                    .show(ui, |plot_ui| {
+                        // Always show all lines as percentage change from start
+                        if !changes.btc.is_empty() && !changes.usd.is_empty() {
+                            plot_ui.set_plot_bounds(egui_plot::PlotBounds::from_min_max(
+                                [x_bounds[0], changes.y_bounds[0]],
+                                [x_bounds[1], changes.y_bounds[1]]
+                            ));
                             
-                        let usd_pct: Vec<[f64; 2]> = usd_points.iter()
-                            .skip(usd_base_idx)
-                            .filter(|p| p[0] >= x_bounds[0] && p[0] <= x_bounds[1])
-                            .map(|p| [p[0], ((p[1] / usd_base) - 1.0) * 100.0])
-                            .collect();
-                        
-                        // Calculate Y-axis bounds from visible data
-                        let mut min_y: f64 = 0.0;
-                        let mut max_y: f64 = 0.0;
-                        
-                        // Check all data series for min/max
-                        for points in [&btc_pct, &usd_pct] {
-                            for p in points {
-                                min_y = min_y.min(p[1]);
-                                max_y = max_y.max(p[1]);
+                            // BTC line
+                            let btc_name = match self.representation_mode {
+                                RepresentationMode::UnitsPerCurrency => format!("{} per BTC (% change)", self.selected_commodity.unit()),
+                                RepresentationMode::PricePerUnit => format!("BTC per {} (% change)", self.selected_commodity.unit()),
+                            };
+                            // Draw USD line first (thicker)
+                            let usd_name = match self.representation_mode {
+                                RepresentationMode::UnitsPerCurrency => format!("{} per Dollar (% change)", self.selected_commodity.unit()),
+                                RepresentationMode::PricePerUnit => format!("USD per {} (% change)", self.selected_commodity.unit()),
+                            };
+                            let line = Line::new(PlotPoints::from(changes.usd.clone()))
+                                .name(usd_name)
+                                .color(egui::Color32::from_rgb(Currency::USD.color.0, Currency::USD.color.1, Currency::USD.color.2))
+                                .width(2.5);
+                            plot_ui.line(line);
+                            
+                            // Then BTC line
+                            let line = Line::new(PlotPoints::from(changes.btc.clone()))
+                                .name(btc_name)
+                                .color(egui::Color32::from_rgb(BTC_COLOR.0, BTC_COLOR.1, BTC_COLOR.2))
+                                .width(2.0);
+                            plot_ui.line(line);
+                            if !changes.btc_carried.is_empty() {
+                                plot_ui.points(carried_markers(changes.btc_carried.clone(), BTC_COLOR));
                             }
-                        }
-                        
-                        // Set the plot bounds first
-                        // Add padding to Y-axis bounds
-                        let y_padding = (max_y - min_y) * 0.1;
-                        if y_padding > 0.0 {
-                            min_y -= y_padding;
-                            max_y += y_padding;
-                        } else {
-                            // If all values are the same, add some default padding
-                            min_y -= 5.0;
-                            max_y += 5.0;
-                        }
-                        
-                        plot_ui.set_plot_bounds(egui_plot::PlotBounds::from_min_max(
-                            [x_bounds[0], min_y],
-                            [x_bounds[1], max_y]
-                        ));
-                        
-                        // BTC line
-                        let btc_name = match self.representation_mode {
-                            RepresentationMode::UnitsPerCurrency => format!("{} per BTC (% change)", self.selected_commodity.unit()),
-                            RepresentationMode::PricePerUnit => format!("BTC per {} (% change)", self.selected_commodity.unit()),
-                        };
-                        // Draw USD line first (thicker)
-                        let usd_name = match self.representation_mode {
-                            RepresentationMode::UnitsPerCurrency => format!("{} per Dollar (% change)", self.selected_commodity.unit()),
-                            RepresentationMode::PricePerUnit => format!("USD per {} (% change)", self.selected_commodity.unit()),
-                        };
-                        let line = Line::new(PlotPoints::from(usd_pct))
-                            .name(usd_name)
-                            .color(egui::Color32::from_rgb(0, 128, 255))
-                            .width(2.5);
-                        plot_ui.line(line);
-                        
-                        // Then BTC line
-                        let line = Line::new(PlotPoints::from(btc_pct))
-                            .name(btc_name)
-                            .color(egui::Color32::from_rgb(255, 165, 0))
-                            .width(2.0);
-                        plot_ui.line(line);
-                        
-                        // CAD line
-                        if !cad_points.is_empty() {
-                            let cad_base = cad_points.get(cad_base_idx).map(|p| p[1]).unwrap_or(1.0);
                             
-                            let cad_pct: Vec<[f64; 2]> = cad_points.iter()
-                                .skip(cad_base_idx)
-                                .filter(|p| p[0] >= x_bounds[0] && p[0] <= x_bounds[1])
-                                .map(|p| [p[0], ((p[1] / cad_base) - 1.0) * 100.0])
-                                .collect();
+                            // One line per enabled currency
+                            for (currency, pct) in &changes.currencies {
+                                let name = match self.representation_mode {
+                                    RepresentationMode::UnitsPerCurrency => format!("{} per {} (% change)", self.selected_commodity.unit(), currency.code),
+                                    RepresentationMode::PricePerUnit => format!("{} per {} (% change)", currency.code, self.selected_commodity.unit()),
+                                };
+                                let (r, g, b) = currency.color;
+                                let line = Line::new(PlotPoints::from(pct.clone()))
+                                    .name(name)
+                                    .color(egui::Color32::from_rgb(r, g, b))
+                                    .width(1.5);
+                                plot_ui.line(line);
+                            }
                             
-                            // Update min/max with CAD data
-                            for p in &cad_pct {
-                                min_y = min_y.min(p[1]);
-                                max_y = max_y.max(p[1]);
+                            // One Bitcoin line per extra basket
+                            for (i, (basket, pct)) in changes.baskets.iter().enumerate() {
+                                let name = match self.representation_mode {
+                                    RepresentationMode::UnitsPerCurrency => format!("{} baskets per BTC (% change)", basket.name()),
+                                    RepresentationMode::PricePerUnit => format!("BTC per {} basket (% change)", basket.name()),
+                                };
+                                let (r, g, b) = BASKET_COLORS[i % BASKET_COLORS.len()];
+                                let line = Line::new(PlotPoints::from(pct.clone()))
+                                    .name(name)
+                                    .color(egui::Color32::from_rgb(r, g, b))
+                                    .style(egui_plot::LineStyle::dashed_loose())
+                                    .width(2.0);
+                                plot_ui.line(line);
+                                if let Some(carried) = changes.baskets_carried.get(i).filter(|c| !c.is_empty()) {
+                                    plot_ui.points(carried_markers(carried.clone(), (r, g, b)));
+                                }
                             }
                             
-                            let cad_name = match self.representation_mode {
-                                RepresentationMode::UnitsPerCurrency => format!("{} per CAD (% change)", self.selected_commodity.unit()),
-                                RepresentationMode::PricePerUnit => format!("CAD per {} (% change)", self.selected_commodity.unit()),
-                            };
-                            let line = Line::new(PlotPoints::from(cad_pct))
-                                .name(cad_name)
-                                .color(egui::Color32::from_rgb(220, 50, 50)) // Red
-                                .width(1.5);
-                            plot_ui.line(line);
+                            // Mark where Bitcoin prices switch from one source to the next
+                            for (source, from, _) in btc_sources.iter().skip(1) {
+                                let x = from.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp() as f64 / 86400.0;
+                                if x >= x_bounds[0] && x <= x_bounds[1] {
+                                    plot_ui.vline(VLine::new(x)
+                                        .name(format!("BTC source: {}", source.unwrap_or("derived")))
+                                        .color(egui::Color32::GRAY)
+                                        .width(1.0));
+                                }
+                            }
                         }
-                    }
-                });
+                    });
+                
+                self.show_stats(ui, &changes.stats);
+                self.show_rolling_chart(ui, &series.key, x_bounds, plot_height * 0.5);
+            }
             
             ui.separator();
             ui.label("Understanding the chart:");
-            match self.representation_mode {
-                RepresentationMode::UnitsPerCurrency => {
-                    ui.label("• All lines show % change in purchasing power from start date");
-                    ui.label("• Orange: How many more/fewer commodity units 1 BTC can buy");
-                    ui.label("• Blue: How many more/fewer commodity units $1 USD can buy");
-                    ui.label("• Red: How many more/fewer commodity units $1 CAD can buy");
-                    ui.label("• Same scale makes comparison fair and accurate");
-                },
-                RepresentationMode::PricePerUnit => {
-                    ui.label("• All lines show % change in price from start date");
-                    ui.label("• Orange: % change in BTC needed per unit of commodity");
-                    ui.label("• Blue: % change in USD needed per unit of commodity");
-                    ui.label("• Red: % change in CAD needed per unit of commodity");
-                    ui.label("• Negative % means commodity got cheaper");
-                },
+            if self.chart_style == ChartStyle::Candlesticks {
+                ui.label("• Each candle shows the day's open, high, low and close in USD");
+                ui.label("• Green candles closed higher than they opened, red candles lower");
+                ui.label("• ATR (average true range) measures typical daily movement");
+            } else if self.chart_style == ChartStyle::Correlations {
+                ui.label("• Each cell is the correlation of two assets' returns over the visible range, at the selected frequency");
+                ui.label("• Red: they tend to move together, blue: in opposite directions, white: unrelated");
+                ui.label("• Each pair uses the dates both have prices on, so weekends don't count against exchange-traded assets");
+            } else if self.chart_style == ChartStyle::Dca {
+                ui.label("• The same amount goes into each line on the first day of every period in the visible range");
+                ui.label("• Bitcoin and the asset are bought at that day's price; cash is converted and held");
+                ui.label("• A line above the asset's own line means its contributions now buy more of the asset than buying it directly did");
+            } else if self.chart_style == ChartStyle::Portfolio {
+                ui.label("• Each position is added on its date; negative amounts are sales");
+                ui.label("• In units of the selected asset, a rising line means the portfolio buys more real goods, whatever the currency does");
+                ui.label(format!("• Positions are saved to {}", self.portfolio_path.display()));
+            } else {
+                match self.representation_mode {
+                    RepresentationMode::UnitsPerCurrency => {
+                        ui.label("• All lines show % change in purchasing power from start date");
+                        ui.label("• Orange: How many more/fewer commodity units 1 BTC can buy");
+                        ui.label("• Blue: How many more/fewer commodity units $1 USD can buy");
+                        ui.label("• Other colors: How many more/fewer commodity units 1 unit of each enabled currency can buy");
+                        ui.label("• Same scale makes comparison fair and accurate");
+                    },
+                    RepresentationMode::PricePerUnit => {
+                        ui.label("• All lines show % change in price from start date");
+                        ui.label("• Orange: % change in BTC needed per unit of commodity");
+                        ui.label("• Blue: % change in USD needed per unit of commodity");
+                        ui.label("• Other colors: % change in each enabled currency needed per unit of commodity");
+                        ui.label("• Negative % means commodity got cheaper");
+                    },
+                }
+                if self.alignment.calendar == Calendar::CarryForward {
+                    ui.label("• Open circles: the commodity did not trade that day, its last price is used");
+                }
             }
             ui.separator();
             ui.label("Use the slider to adjust the start date of the chart");
@@ -390,6 +1827,57 @@ impl eframe::App for PurchasingPowerApp {
 }
 
 fn main() -> Result<(), eframe::Error> {
+    let args: Vec<String> = std::env::args().collect();
+    
+    // --assets path.json (or $ASSETS_CONFIG, or ./assets.json) replaces the built-in asset list
+    let asset_config = args.iter()
+        .position(|arg| arg == "--assets")
+        .and_then(|i| args.get(i + 1).cloned())
+        .or_else(|| std::env::var("ASSETS_CONFIG").ok())
+        .map(PathBuf::from)
+        .or_else(|| Some(PathBuf::from("assets.json")).filter(|path| path.exists()));
+    if let Some(path) = asset_config {
+        match AssetRegistry::from_file(&path) {
+            Ok(registry) => {
+                eprintln!("Loaded {} assets from {}", registry.assets().len(), path.display());
+                registry.install();
+            }
+            Err(e) => eprintln!("{:#}, using built-in assets", e),
+        }
+    }
+    
+    // Headless commands (series, compare, fetch, ...) print results instead of opening a window
+    if let Some(result) = cli::run(&args) {
+        if let Err(e) = result {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    
+    // --currencies CAD,EUR (or $CURRENCIES) picks the currencies drawn next to USD
+    let currency_list = args.iter()
+        .position(|arg| arg == "--currencies")
+        .and_then(|i| args.get(i + 1).cloned())
+        .or_else(|| std::env::var("CURRENCIES").ok())
+        .unwrap_or_else(|| "CAD".to_string());
+    let currencies = Currency::parse_list(&currency_list);
+    
+    // --full-refresh ignores cached prices and refetches the full history
+    let cache = PriceCache::default_location()
+        .with_full_refresh(args.iter().any(|arg| arg == "--full-refresh"));
+    if args.iter().any(|arg| arg == "--clear-cache") {
+        if let Err(e) = cache.clear() {
+            eprintln!("Failed to clear price cache: {}", e);
+        }
+    }
+    
+    // --portfolio path.json (or $PORTFOLIO, or ./portfolio.json) holds the Portfolio view's positions
+    let portfolio_path = args.iter()
+        .position(|arg| arg == "--portfolio")
+        .and_then(|i| args.get(i + 1).map(PathBuf::from))
+        .unwrap_or_else(Portfolio::default_path);
+    
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([1200.0, 800.0]),
@@ -399,6 +1887,6 @@ fn main() -> Result<(), eframe::Error> {
     eframe::run_native(
         "Bitcoin Purchasing Power Tracker",
         native_options,
-        Box::new(|_cc| Box::new(PurchasingPowerApp::new())),
+        Box::new(|cc| Box::new(PurchasingPowerApp::new(&cc.egui_ctx, cache, currencies, portfolio_path))),
     )
 }
\ No newline at end of file
@@ -0,0 +1,52 @@
+use chrono::NaiveDate;
+use anyhow::Result;
+use crate::models::Asset;
+
+/// Basket weights are percentages and must add up to this
+pub const WEIGHT_TOTAL: f64 = 100.0;
+
+// Slack for weights typed as decimals, e.g. 33.33 + 33.33 + 33.34
+const WEIGHT_TOLERANCE: f64 = 0.01;
+
+/// A named consumer basket: weighted assets normalized at a base date
+#[derive(Debug, Clone, PartialEq)]
+pub struct Basket {
+    /// Derived asset the basket's series is stored and plotted under
+    pub asset: Asset,
+    pub name: String,
+    /// Date each component's price is normalized at; `None` uses the first date
+    /// every component has a price
+    pub base_date: Option<NaiveDate>,
+    /// (asset, weight in percent)
+    pub components: Vec<(Asset, f64)>,
+}
+
+impl Basket {
+    pub fn total_weight(&self) -> f64 {
+        self.components.iter().map(|(_, weight)| weight).sum()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.components.is_empty() {
+            return Err(anyhow::anyhow!("Basket {} has no components", self.name));
+        }
+        if let Some((asset, weight)) = self.components.iter().find(|(_, weight)| *weight < 0.0) {
+            return Err(anyhow::anyhow!("Basket {} has a negative weight {} for {}", self.name, weight, asset.id()));
+        }
+        let total = self.total_weight();
+        if (total - WEIGHT_TOTAL).abs() > WEIGHT_TOLERANCE {
+            return Err(anyhow::anyhow!("Basket {} weights add up to {:.2}%, expected {}%", self.name, total, WEIGHT_TOTAL));
+        }
+        Ok(())
+    }
+
+    /// Scale every weight so they add up to `WEIGHT_TOTAL`
+    pub fn normalize_weights(&mut self) {
+        let total = self.total_weight();
+        if total > 0.0 {
+            for (_, weight) in self.components.iter_mut() {
+                *weight *= WEIGHT_TOTAL / total;
+            }
+        }
+    }
+}
@@ -1,5 +1,11 @@
+pub mod registry;
+pub mod basket;
+
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use self::basket::Basket;
+use self::registry::{AssetDefinition, AssetRegistry, SourceTicker};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AssetPrice {
@@ -8,91 +14,156 @@ pub struct AssetPrice {
     pub timestamp: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
-pub enum Asset {
-    Bitcoin,
-    Gold,
-    Wheat,
-    Corn,
-    Beef,
-    Coffee,
-    Rice,
-    Oil,
-    NaturalGas,
-    Copper,
-    Silver,
-    Soybeans,
-    Sugar,
-    Cotton,
-    Lumber,
-    ConsumerBasket, // Blended index
-}
+/// Handle to an asset defined in the `AssetRegistry` (see `assets.json`)
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Asset(&'static str);
 
 impl Asset {
+    /// Every comparison is made against Bitcoin, so it has a fixed id
+    pub const BITCOIN: Asset = Asset("bitcoin");
+
+    /// Every asset fetched from a price source (baskets are derived)
+    pub fn tracked() -> &'static [Asset] {
+        AssetRegistry::global().assets()
+    }
+
+    /// Every consumer basket, derived from the tracked assets
+    pub fn baskets() -> impl Iterator<Item = Asset> {
+        AssetRegistry::global().baskets().iter().map(|basket| basket.asset)
+    }
+
+    pub fn from_id(id: &str) -> Option<Asset> {
+        AssetRegistry::global().find(id)
+    }
+
+    pub fn id(&self) -> &'static str {
+        self.0
+    }
+
+    pub fn definition(&self) -> Option<&'static AssetDefinition> {
+        AssetRegistry::global().definition(*self)
+    }
+
+    /// The basket's configured weights, if this asset is a consumer basket
+    pub fn basket(&self) -> Option<&'static Basket> {
+        AssetRegistry::global().basket(*self)
+    }
+
+    pub fn is_basket(&self) -> bool {
+        self.basket().is_some()
+    }
+
+    /// Ticker and scaling used by `source` ("yahoo", "coingecko", ...) for this asset
+    pub fn source(&self, source: &str) -> Option<&'static SourceTicker> {
+        self.definition()?.sources.get(source)
+    }
+
+    /// Currency the asset's sources quote in, USD unless configured otherwise
+    pub fn quote_currency(&self) -> Currency {
+        self.definition()
+            .and_then(|definition| definition.currency.as_deref())
+            .and_then(Currency::from_code)
+            .unwrap_or(Currency::USD)
+    }
+
     pub fn name(&self) -> &'static str {
-        match self {
-            Asset::Bitcoin => "Bitcoin",
-            Asset::Gold => "Gold (per oz)",
-            Asset::Wheat => "Wheat (per bushel)",
-            Asset::Corn => "Corn (per bushel)",
-            Asset::Beef => "Beef (per lb)",
-            Asset::Coffee => "Coffee (per lb)",
-            Asset::Rice => "Rice (per cwt)",
-            Asset::Oil => "Crude Oil (per barrel)",
-            Asset::NaturalGas => "Natural Gas (per MMBtu)",
-            Asset::Copper => "Copper (per lb)",
-            Asset::Silver => "Silver (per oz)",
-            Asset::Soybeans => "Soybeans (per bushel)",
-            Asset::Sugar => "Sugar (per lb)",
-            Asset::Cotton => "Cotton (per lb)",
-            Asset::Lumber => "Lumber (per 1000 bd ft)",
-            Asset::ConsumerBasket => "Consumer Basket (Blended)",
+        match (self.definition(), self.basket()) {
+            (Some(definition), _) => &definition.name,
+            (None, Some(basket)) => &basket.name,
+            (None, None) => self.0,
         }
     }
     
     pub fn base_name(&self) -> &'static str {
-        match self {
-            Asset::Bitcoin => "Bitcoin",
-            Asset::Gold => "Gold",
-            Asset::Wheat => "Wheat",
-            Asset::Corn => "Corn",
-            Asset::Beef => "Beef",
-            Asset::Coffee => "Coffee",
-            Asset::Rice => "Rice",
-            Asset::Oil => "Oil",
-            Asset::NaturalGas => "Natural Gas",
-            Asset::Copper => "Copper",
-            Asset::Silver => "Silver",
-            Asset::Soybeans => "Soybeans",
-            Asset::Sugar => "Sugar",
-            Asset::Cotton => "Cotton",
-            Asset::Lumber => "Lumber",
-            Asset::ConsumerBasket => "Consumer Basket",
+        match (self.definition(), self.basket()) {
+            (Some(definition), _) => &definition.short_name,
+            (None, Some(basket)) => &basket.name,
+            (None, None) => self.0,
         }
     }
     
     pub fn unit(&self) -> &'static str {
-        match self {
-            Asset::Bitcoin => "BTC",
-            Asset::Gold => "oz",
-            Asset::Wheat => "bushel",
-            Asset::Corn => "bushel",
-            Asset::Beef => "lb",
-            Asset::Coffee => "lb",
-            Asset::Rice => "cwt",
-            Asset::Oil => "barrel",
-            Asset::NaturalGas => "MMBtu",
-            Asset::Copper => "lb",
-            Asset::Silver => "oz",
-            Asset::Soybeans => "bushel",
-            Asset::Sugar => "lb",
-            Asset::Cotton => "lb",
-            Asset::Lumber => "1000 bd ft",
-            Asset::ConsumerBasket => "basket",
+        match (self.definition(), self.basket()) {
+            (Some(definition), _) => &definition.unit,
+            (None, Some(_)) => "basket",
+            (None, None) => "unit",
+        }
+    }
+
+    pub fn category(&self) -> &'static str {
+        match (self.definition(), self.basket()) {
+            (Some(definition), _) => &definition.category,
+            (None, Some(_)) => "Baskets",
+            (None, None) => "Other",
         }
     }
 }
 
+impl fmt::Debug for Asset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.base_name())
+    }
+}
+
+impl Serialize for Asset {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Asset {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        Asset::from_id(&id).ok_or_else(|| serde::de::Error::custom(format!("unknown asset {}", id)))
+    }
+}
+
+/// A fiat currency that prices can be expressed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Currency {
+    pub code: &'static str,
+    pub name: &'static str,
+    pub color: (u8, u8, u8), // Line color in the chart
+}
+
+impl Currency {
+    pub const USD: Currency = Currency { code: "USD", name: "US Dollar", color: (0, 128, 255) };
+
+    /// Every currency that can be enabled; prices are fetched in USD and converted
+    pub fn all() -> &'static [Currency] {
+        &[
+            Currency::USD,
+            Currency { code: "CAD", name: "Canadian Dollar", color: (220, 50, 50) },
+            Currency { code: "EUR", name: "Euro", color: (0, 160, 120) },
+            Currency { code: "GBP", name: "British Pound", color: (140, 70, 200) },
+            Currency { code: "JPY", name: "Japanese Yen", color: (230, 120, 170) },
+            Currency { code: "CHF", name: "Swiss Franc", color: (120, 120, 120) },
+            Currency { code: "AUD", name: "Australian Dollar", color: (180, 140, 40) },
+        ]
+    }
+
+    pub fn from_code(code: &str) -> Option<Currency> {
+        Self::all().iter().copied().find(|c| c.code.eq_ignore_ascii_case(code))
+    }
+
+    /// Enabled currencies from a comma separated list like "CAD,EUR", skipping unknown
+    /// codes and USD (which is always shown)
+    pub fn parse_list(codes: &str) -> Vec<Currency> {
+        codes.split(',')
+            .map(str::trim)
+            .filter(|code| !code.is_empty() && !code.eq_ignore_ascii_case(Currency::USD.code))
+            .filter_map(|code| {
+                let currency = Self::from_code(code);
+                if currency.is_none() {
+                    eprintln!("Unknown currency {}, supported: {}", code,
+                              Self::all().iter().map(|c| c.code).collect::<Vec<_>>().join(", "));
+                }
+                currency
+            })
+            .collect()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PriceComparison {
     pub base_asset: Asset,
@@ -107,3 +178,22 @@ pub enum RepresentationMode {
     PricePerUnit,      // Price in BTC/Dollars per 1 unit of asset
 }
 
+impl RepresentationMode {
+    /// "price"/"PricePerUnit" or "units"/"UnitsPerCurrency", ignoring case
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "price" | "priceperunit" => Some(Self::PricePerUnit),
+            "units" | "unitspercurrency" => Some(Self::UnitsPerCurrency),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartStyle {
+    PercentChange,  // BTC and currency lines as % change from the start date
+    Candlesticks,   // Daily OHLC bars of a single asset in USD
+    Correlations,   // Heatmap of return correlations between every loaded asset
+    Dca,            // Periodic contributions into BTC, the asset and cash, valued over time
+    Portfolio,      // The user's holdings valued over time
+}
@@ -0,0 +1,219 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+use anyhow::{Context, Result};
+use crate::models::{Asset, Currency};
+use crate::models::basket::Basket;
+
+// Built-in asset definitions, used when no config file is given
+const DEFAULT_CONFIG: &str = include_str!("../../assets.json");
+
+static REGISTRY: OnceLock<AssetRegistry> = OnceLock::new();
+
+/// How one source identifies an asset
+#[derive(Debug, Clone, Deserialize)]
+pub struct SourceTicker {
+    pub ticker: String,
+    /// Multiplier from the quoted price to the unit price, e.g. 0.01 for cents
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    /// First date the source has data for, if known
+    #[serde(default)]
+    pub since: Option<NaiveDate>,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+/// Price band for generated sample data when no real source is reachable
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct SampleRange {
+    pub min: f64,
+    pub max: f64,
+    /// Add a yearly cycle, e.g. for crops
+    #[serde(default)]
+    pub seasonal: bool,
+}
+
+/// How observations less frequent than daily (e.g. monthly CPI) fill the days between them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FillMethod {
+    /// Hold each value until the next observation
+    #[default]
+    Step,
+    /// Interpolate linearly between observations
+    Linear,
+}
+
+/// A series read from a CSV file, e.g. a FRED or Statistics Canada download
+#[derive(Debug, Clone, Deserialize)]
+pub struct CsvSeries {
+    /// Local path or http(s) URL
+    pub path: String,
+    /// Column holding dates; defaults to DATE, observation_date or REF_DATE, else the first column
+    #[serde(default)]
+    pub date_column: Option<String>,
+    /// Column holding values; defaults to VALUE, else the last column
+    #[serde(default)]
+    pub value_column: Option<String>,
+    /// Only use rows whose columns have these values, e.g. {"GEO": "Canada"}
+    #[serde(default)]
+    pub filter: HashMap<String, String>,
+    #[serde(default)]
+    pub fill: FillMethod,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AssetDefinition {
+    pub id: String,
+    pub name: String,
+    pub short_name: String,
+    pub unit: String,
+    pub category: String,
+    /// Tickers keyed by source ("yahoo", "coingecko", "coindesk")
+    #[serde(default)]
+    pub sources: HashMap<String, SourceTicker>,
+    #[serde(default)]
+    pub sample: Option<SampleRange>,
+    #[serde(default)]
+    pub csv: Option<CsvSeries>,
+    /// Currency the sources quote in when not USD; prices are converted to USD on load
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct BasketComponentConfig {
+    asset: String,
+    weight: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct BasketConfig {
+    id: String,
+    name: String,
+    #[serde(default)]
+    base_date: Option<NaiveDate>,
+    components: Vec<BasketComponentConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RegistryConfig {
+    assets: Vec<AssetDefinition>,
+    #[serde(default)]
+    baskets: Vec<BasketConfig>,
+}
+
+/// Every asset the app knows about, loaded once at startup
+#[derive(Debug)]
+pub struct AssetRegistry {
+    assets: Vec<Asset>,
+    definitions: HashMap<Asset, AssetDefinition>,
+    baskets: Vec<Basket>,
+}
+
+// Ids live for the whole run, leaking them lets `Asset` stay `Copy`
+fn leak_asset(id: &str) -> Asset {
+    Asset(Box::leak(id.to_string().into_boxed_str()))
+}
+
+impl AssetRegistry {
+    pub fn from_json(json: &str) -> Result<Self> {
+        let config: RegistryConfig = serde_json::from_str(json)?;
+
+        let mut assets = Vec::new();
+        let mut definitions = HashMap::new();
+        for definition in config.assets {
+            if assets.iter().any(|a: &Asset| a.id() == definition.id) {
+                return Err(anyhow::anyhow!("Duplicate asset id {}", definition.id));
+            }
+            if let Some(code) = &definition.currency {
+                if Currency::from_code(code).is_none() {
+                    return Err(anyhow::anyhow!("Asset {} is quoted in unknown currency {}", definition.id, code));
+                }
+            }
+            let asset = match definition.id.as_str() {
+                id if id == Asset::BITCOIN.id() => Asset::BITCOIN,
+                id => leak_asset(id),
+            };
+            assets.push(asset);
+            definitions.insert(asset, definition);
+        }
+
+        let mut baskets = Vec::new();
+        for preset in config.baskets {
+            if assets.iter().chain(baskets.iter().map(|b: &Basket| &b.asset)).any(|a| a.id() == preset.id) {
+                return Err(anyhow::anyhow!("Basket id {} is already used", preset.id));
+            }
+            let mut components = Vec::new();
+            for component in preset.components {
+                let asset = assets.iter()
+                    .copied()
+                    .find(|a| a.id() == component.asset)
+                    .ok_or_else(|| anyhow::anyhow!("Basket {} refers to unknown asset {}", preset.name, component.asset))?;
+                components.push((asset, component.weight));
+            }
+            let basket = Basket {
+                asset: leak_asset(&preset.id),
+                name: preset.name,
+                base_date: preset.base_date,
+                components,
+            };
+            basket.validate()?;
+            baskets.push(basket);
+        }
+
+        Ok(Self { assets, definitions, baskets })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::from_json(&json).with_context(|| format!("Invalid asset config {}", path.display()))
+    }
+
+    pub fn builtin() -> Self {
+        Self::from_json(DEFAULT_CONFIG).expect("built-in assets.json is valid")
+    }
+
+    /// Install the registry used by `Asset`; only the first call has an effect
+    pub fn install(self) {
+        if REGISTRY.set(self).is_err() {
+            eprintln!("Asset registry already loaded, ignoring new definitions");
+        }
+    }
+
+    /// The installed registry, or the built-in one if nothing was installed
+    pub fn global() -> &'static AssetRegistry {
+        REGISTRY.get_or_init(Self::builtin)
+    }
+
+    pub fn assets(&self) -> &[Asset] {
+        &self.assets
+    }
+
+    pub fn definition(&self, asset: Asset) -> Option<&AssetDefinition> {
+        self.definitions.get(&asset)
+    }
+
+    /// Fetched asset or basket with the given id
+    pub fn find(&self, id: &str) -> Option<Asset> {
+        self.assets.iter()
+            .chain(self.baskets.iter().map(|b| &b.asset))
+            .copied()
+            .find(|a| a.id().eq_ignore_ascii_case(id))
+    }
+
+    /// Consumer baskets in config order
+    pub fn baskets(&self) -> &[Basket] {
+        &self.baskets
+    }
+
+    pub fn basket(&self, asset: Asset) -> Option<&Basket> {
+        self.baskets.iter().find(|b| b.asset == asset)
+    }
+}
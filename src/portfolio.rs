@@ -0,0 +1,319 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use crate::models::{Asset, Currency};
+use crate::data::PriceData;
+use crate::data::fx::ExchangeRates;
+use crate::data::align::{Alignment, DateIndex};
+use crate::series::{SeriesPoint, SeriesTable};
+
+/// Something a position holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Instrument {
+    Asset(Asset),
+    Cash(Currency),
+}
+
+impl Instrument {
+    /// Column prefix, e.g. "bitcoin", "gold" or "cad"
+    pub fn key(&self) -> String {
+        match self {
+            Self::Asset(asset) => asset.id().to_string(),
+            Self::Cash(currency) => currency.code.to_ascii_lowercase(),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Self::Asset(asset) => asset.base_name().to_string(),
+            Self::Cash(currency) => format!("{} cash", currency.code),
+        }
+    }
+
+    /// Units of an amount, e.g. "BTC", "oz" or "CAD"
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Self::Asset(asset) if *asset == Asset::BITCOIN => "BTC",
+            Self::Asset(asset) => asset.unit(),
+            Self::Cash(currency) => currency.code,
+        }
+    }
+}
+
+/// An amount bought (or sold, when negative) on a date
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Position {
+    pub instrument: Instrument,
+    pub amount: f64,
+    pub date: NaiveDate,
+}
+
+// One position as stored: either `asset` (an asset id) or `cash` (a currency code)
+#[derive(Debug, Serialize, Deserialize)]
+struct PositionConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    asset: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cash: Option<String>,
+    amount: f64,
+    date: NaiveDate,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PortfolioConfig {
+    positions: Vec<PositionConfig>,
+}
+
+/// Holdings entered by the user, kept in a local JSON file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Portfolio {
+    pub positions: Vec<Position>,
+}
+
+impl Portfolio {
+    /// `$PORTFOLIO`, or portfolio.json in the working directory
+    pub fn default_path() -> PathBuf {
+        std::env::var("PORTFOLIO").map(PathBuf::from).unwrap_or_else(|_| PathBuf::from("portfolio.json"))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let config: PortfolioConfig = serde_json::from_str(json)?;
+        let positions = config.positions.into_iter()
+            .map(|position| {
+                let instrument = match (&position.asset, &position.cash) {
+                    (Some(id), None) => Instrument::Asset(Asset::from_id(id)
+                        .ok_or_else(|| anyhow::anyhow!("Unknown asset {}", id))?),
+                    (None, Some(code)) => Instrument::Cash(Currency::from_code(code)
+                        .ok_or_else(|| anyhow::anyhow!("Unknown currency {}", code))?),
+                    _ => return Err(anyhow::anyhow!("Position on {} needs either an asset or a cash currency", position.date)),
+                };
+                Ok(Position { instrument, amount: position.amount, date: position.date })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { positions })
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        let config = PortfolioConfig {
+            positions: self.positions.iter()
+                .map(|position| {
+                    let (asset, cash) = match position.instrument {
+                        Instrument::Asset(asset) => (Some(asset.id().to_string()), None),
+                        Instrument::Cash(currency) => (None, Some(currency.code.to_string())),
+                    };
+                    PositionConfig { asset, cash, amount: position.amount, date: position.date }
+                })
+                .collect(),
+        };
+        Ok(serde_json::to_string_pretty(&config)?)
+    }
+
+    /// The portfolio in `path`, or an empty one if the file doesn't exist yet
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::from_json(&json).with_context(|| format!("Invalid portfolio {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.to_json()?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Each instrument once, in the order first entered
+    pub fn instruments(&self) -> Vec<Instrument> {
+        let mut instruments: Vec<Instrument> = Vec::new();
+        for position in &self.positions {
+            if !instruments.contains(&position.instrument) {
+                instruments.push(position.instrument);
+            }
+        }
+        instruments
+    }
+}
+
+/// The portfolio on one date
+#[derive(Debug, Clone)]
+pub struct Valuation {
+    pub date: NaiveDate,
+    /// Amount held of each instrument, in the order of `PortfolioHistory::instruments`
+    pub held: Vec<f64>,
+    /// USD value of each instrument's holding, in the same order
+    pub parts_usd: Vec<f64>,
+    /// Units of the display currency per USD
+    pub rate: f64,
+    /// USD price of one unit of the measured asset
+    pub asset_usd: f64,
+}
+
+impl Valuation {
+    pub fn total_usd(&self) -> f64 {
+        self.parts_usd.iter().sum()
+    }
+
+    /// Total in the display currency
+    pub fn value(&self) -> f64 {
+        self.total_usd() * self.rate
+    }
+
+    /// Total in units of the measured asset
+    pub fn value_in_asset(&self) -> f64 {
+        self.total_usd() / self.asset_usd
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PortfolioHistory {
+    pub instruments: Vec<Instrument>,
+    pub points: Vec<Valuation>,
+}
+
+/// Value the portfolio on every Bitcoin date from its first position, in `currency` and in
+/// units of `asset`. Asset prices are matched to the dates by `alignment`; dates on which a
+/// held instrument, the asset or the currency has no price are skipped.
+pub fn value(
+    data: &HashMap<Asset, Vec<PriceData>>,
+    fx: &ExchangeRates,
+    portfolio: &Portfolio,
+    currency: Currency,
+    asset: Asset,
+    alignment: Alignment,
+) -> PortfolioHistory {
+    let instruments = portfolio.instruments();
+    let (Some(btc_data), Some(asset_data), Some(start)) = (
+        data.get(&Asset::BITCOIN),
+        data.get(&asset),
+        portfolio.positions.iter().map(|p| p.date).min(),
+    ) else {
+        return PortfolioHistory { instruments, points: Vec::new() };
+    };
+    let dates: Vec<NaiveDate> = btc_data.iter().map(|p| p.date).filter(|date| *date >= start).collect();
+
+    // USD price of one unit of each instrument on each date
+    let unit_prices: Vec<Vec<Option<f64>>> = instruments.iter()
+        .map(|instrument| match instrument {
+            Instrument::Asset(held) => match data.get(held) {
+                Some(prices) => DateIndex::from_prices(prices).join(dates.iter().copied(), alignment)
+                    .map(|(_, price)| price)
+                    .collect(),
+                None => vec![None; dates.len()],
+            },
+            Instrument::Cash(cash) => fx.rates(*cash, dates.iter().copied()).into_iter()
+                .map(|rate| rate.filter(|r| *r > 0.0).map(|r| 1.0 / r))
+                .collect(),
+        })
+        .collect();
+    let asset_prices: Vec<Option<f64>> = DateIndex::from_prices(asset_data).join(dates.iter().copied(), alignment)
+        .map(|(_, price)| price)
+        .collect();
+    let rates = fx.rates(currency, dates.iter().copied());
+
+    let mut positions = portfolio.positions.clone();
+    positions.sort_by_key(|p| p.date);
+    let mut pending = positions.iter().peekable();
+    let mut held = vec![0.0; instruments.len()];
+    let mut points = Vec::new();
+    for (i, &date) in dates.iter().enumerate() {
+        while let Some(position) = pending.next_if(|p| p.date <= date) {
+            let index = instruments.iter().position(|x| *x == position.instrument).unwrap();
+            held[index] += position.amount;
+        }
+        let (Some(asset_usd), Some(rate)) = (asset_prices[i].filter(|p| *p > 0.0), rates[i]) else {
+            continue;
+        };
+        // Instruments not held yet don't need a price
+        let parts_usd: Option<Vec<f64>> = held.iter().zip(&unit_prices)
+            .map(|(held, prices)| if *held == 0.0 { Some(0.0) } else { prices[i].map(|price| held * price) })
+            .collect();
+        let Some(parts_usd) = parts_usd else {
+            continue;
+        };
+        points.push(Valuation { date, held: held.clone(), parts_usd, rate, asset_usd });
+    }
+    PortfolioHistory { instruments, points }
+}
+
+/// One row per date: the total `value` in the display currency and `in_{asset}`, then each
+/// instrument's `{key}_held` and `{key}_value` in the display currency
+pub fn table(history: &PortfolioHistory, asset: Asset) -> SeriesTable {
+    let column = |value: &dyn Fn(&Valuation) -> f64| -> Vec<SeriesPoint> {
+        history.points.iter().map(|p| (p.date, value(p))).collect()
+    };
+    let mut series = vec![
+        ("value".to_string(), column(&|p| p.value())),
+        (format!("in_{}", asset.id()), column(&|p| p.value_in_asset())),
+    ];
+    for (i, instrument) in history.instruments.iter().enumerate() {
+        series.push((format!("{}_held", instrument.key()), column(&|p| p.held[i])));
+        series.push((format!("{}_value", instrument.key()), column(&|p| p.parts_usd[i] * p.rate)));
+    }
+    SeriesTable::from_series(series)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::align::AlignPolicy;
+    use crate::data::fixtures::{daily, day};
+
+    #[test]
+    fn json_round_trip() {
+        let cad = Currency::from_code("CAD").unwrap();
+        let portfolio = Portfolio {
+            positions: vec![
+                Position { instrument: Instrument::Asset(Asset::BITCOIN), amount: 0.5, date: day(1) },
+                Position { instrument: Instrument::Cash(cad), amount: 1000.0, date: day(3) },
+                Position { instrument: Instrument::Asset(Asset::BITCOIN), amount: -0.25, date: day(4) },
+            ],
+        };
+        assert_eq!(Portfolio::from_json(&portfolio.to_json().unwrap()).unwrap(), portfolio);
+    }
+
+    #[test]
+    fn position_needs_exactly_one_instrument() {
+        let both = r#"{"positions": [{"asset": "gold", "cash": "CAD", "amount": 1.0, "date": "2024-01-01"}]}"#;
+        assert!(Portfolio::from_json(both).is_err());
+        let neither = r#"{"positions": [{"amount": 1.0, "date": "2024-01-01"}]}"#;
+        assert!(Portfolio::from_json(neither).is_err());
+        let unknown = r#"{"positions": [{"asset": "unobtainium", "amount": 1.0, "date": "2024-01-01"}]}"#;
+        assert!(Portfolio::from_json(unknown).is_err());
+    }
+
+    #[test]
+    fn positions_count_from_their_date() {
+        let gold = Asset::from_id("gold").unwrap();
+        let cad = Currency::from_code("CAD").unwrap();
+        let data = HashMap::from([
+            (Asset::BITCOIN, daily(&[40000.0, 42000.0, 44000.0, 46000.0, 48000.0])),
+            (gold, daily(&[2000.0, 2000.0, 2000.0, 2000.0, 2000.0])),
+        ]);
+        let mut fx = ExchangeRates::default();
+        fx.insert(cad, &daily(&[1.25, 1.25, 1.25, 1.25, 1.25]));
+        // Bought 1 BTC on the 2nd, CAD cash from the 3rd and half the Bitcoin sold on the 4th
+        let portfolio = Portfolio {
+            positions: vec![
+                Position { instrument: Instrument::Cash(cad), amount: 2500.0, date: day(3) },
+                Position { instrument: Instrument::Asset(Asset::BITCOIN), amount: 1.0, date: day(2) },
+                Position { instrument: Instrument::Asset(Asset::BITCOIN), amount: -0.5, date: day(4) },
+            ],
+        };
+        let history = value(&data, &fx, &portfolio, Currency::USD, gold, Alignment::new(AlignPolicy::Exact, 0));
+        assert_eq!(history.instruments, vec![Instrument::Cash(cad), Instrument::Asset(Asset::BITCOIN)]);
+
+        let dates: Vec<NaiveDate> = history.points.iter().map(|p| p.date).collect();
+        assert_eq!(dates, vec![day(2), day(3), day(4), day(5)]);
+        let held: Vec<Vec<f64>> = history.points.iter().map(|p| p.held.clone()).collect();
+        assert_eq!(held, vec![vec![0.0, 1.0], vec![2500.0, 1.0], vec![2500.0, 0.5], vec![2500.0, 0.5]]);
+        let totals: Vec<f64> = history.points.iter().map(|p| p.total_usd()).collect();
+        assert_eq!(totals, vec![42000.0, 46000.0, 25000.0, 26000.0]);
+        assert_eq!(history.points[3].value_in_asset(), 13.0);
+    }
+}
@@ -0,0 +1,164 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::path::Path;
+use std::sync::Once;
+use crate::series::{ChartLine, SeriesPoint};
+
+/// Image formats the chart can be rendered to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Svg,
+}
+
+impl ImageFormat {
+    pub fn all() -> [ImageFormat; 2] {
+        [ImageFormat::Png, ImageFormat::Svg]
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "png" => Some(Self::Png),
+            "svg" => Some(Self::Svg),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Svg => "svg",
+        }
+    }
+}
+
+/// A static version of the % change chart, drawn without a window
+pub struct ChartImage {
+    pub title: String,
+    pub lines: Vec<ChartLine>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    /// Dates where Bitcoin prices switch source, with the new source's name
+    pub markers: Vec<(NaiveDate, String)>,
+    pub size: (u32, u32),
+}
+
+// Bitmaps have no fonts of their own; use the one bundled with egui so rendering
+// works on servers without any fonts installed
+fn register_font() {
+    static REGISTER: Once = Once::new();
+    REGISTER.call_once(|| {
+        let fonts = egui::FontDefinitions::default();
+        let font = fonts.families.get(&egui::FontFamily::Proportional)
+            .and_then(|names| names.first())
+            .and_then(|name| fonts.font_data.get(name));
+        if let Some(font) = font {
+            let bytes: &'static [u8] = Box::leak(font.font.to_vec().into_boxed_slice());
+            if plotters::style::register_font("sans-serif", FontStyle::Normal, bytes).is_err() {
+                eprintln!("Failed to load the chart font, text will be missing from PNG charts");
+            }
+        }
+    });
+}
+
+impl ChartImage {
+    /// Render to `path`, creating its directory if needed
+    pub fn write(&self, path: &Path, format: ImageFormat) -> Result<()> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        match format {
+            ImageFormat::Png => {
+                register_font();
+                let root = BitMapBackend::new(path, self.size).into_drawing_area();
+                self.draw(&root)?;
+                root.present()?;
+            }
+            ImageFormat::Svg => {
+                let root = SVGBackend::new(path, self.size).into_drawing_area();
+                self.draw(&root)?;
+                root.present()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<()>
+    where
+        DB::ErrorType: 'static,
+    {
+        let series: Vec<(&ChartLine, Vec<SeriesPoint>)> = self.lines.iter()
+            .map(|line| (line, line.percent_change(self.from, self.to)))
+            .filter(|(_, points)| !points.is_empty())
+            .collect();
+        let all_points = || series.iter().flat_map(|(_, points)| points.iter());
+        let (Some(first), Some(last)) = (all_points().map(|p| p.0).min(), all_points().map(|p| p.0).max()) else {
+            return Err(anyhow::anyhow!("No data to draw for the requested dates"));
+        };
+
+        // Same y range as the app: include 0% and pad by 10%
+        let min_y = all_points().map(|p| p.1).fold(0.0, f64::min);
+        let max_y = all_points().map(|p| p.1).fold(0.0, f64::max);
+        let padding = if max_y > min_y { (max_y - min_y) * 0.1 } else { 5.0 };
+
+        root.fill(&WHITE)?;
+        let mut chart = ChartBuilder::on(root)
+            .caption(&self.title, ("sans-serif", 24))
+            .margin(16)
+            .margin_right(40)
+            .x_label_area_size(40)
+            .y_label_area_size(70)
+            .build_cartesian_2d(first..last, (min_y - padding)..(max_y + padding))
+            .context("Failed to lay out chart")?;
+        chart.configure_mesh()
+            .x_labels(8)
+            .x_label_formatter(&|date| date.format("%Y-%m").to_string())
+            .y_label_formatter(&|value| format!("{:.1}%", value))
+            .light_line_style(RGBColor(235, 235, 235))
+            .label_style(("sans-serif", 14))
+            .draw()?;
+
+        chart.draw_series(LineSeries::new([(first, 0.0), (last, 0.0)], BLACK.mix(0.4)))?;
+        for (date, source) in self.markers.iter().filter(|(date, _)| *date >= first && *date <= last) {
+            let style = RGBColor(150, 150, 150).stroke_width(1);
+            chart.draw_series(DashedLineSeries::new([(*date, min_y - padding), (*date, max_y + padding)], 4, 4, style))?;
+            chart.draw_series(std::iter::once(Text::new(
+                format!("BTC source: {}", source),
+                (*date, min_y - padding * 0.5),
+                ("sans-serif", 12).into_font().color(&RGBColor(110, 110, 110)),
+            )))?;
+        }
+
+        for (line, points) in &series {
+            let (r, g, b) = line.color;
+            let style = RGBColor(r, g, b).stroke_width(line.width.round().max(1.0) as u32);
+            let annotation = if line.dashed {
+                chart.draw_series(DashedLineSeries::new(points.iter().copied(), 8, 6, style))?
+            } else {
+                chart.draw_series(LineSeries::new(points.iter().copied(), style))?
+            };
+            annotation
+                .label(format!("{} (% change)", line.name))
+                .legend(move |(x, y)| PathElement::new([(x, y), (x + 20, y)], style));
+
+            // Open circles where the price was carried over a non-trading day
+            let carried = line.carried_changes(self.from, self.to);
+            if !carried.is_empty() {
+                let marker = RGBColor(r, g, b).stroke_width(1);
+                chart.draw_series(carried.into_iter().map(|point| Circle::new(point, 2, marker)))?
+                    .label(format!("{} (carried, no trading)", line.name))
+                    .legend(move |(x, y)| Circle::new((x + 10, y), 3, marker));
+            }
+        }
+
+        chart.configure_series_labels()
+            .position(SeriesLabelPosition::UpperLeft)
+            .background_style(WHITE.mix(0.85))
+            .border_style(RGBColor(200, 200, 200))
+            .label_font(("sans-serif", 14))
+            .draw()?;
+        Ok(())
+    }
+}
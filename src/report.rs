@@ -0,0 +1,251 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use crate::models::{Asset, Currency, RepresentationMode};
+use crate::data::{source_ranges, PriceData};
+use crate::data::fx::ExchangeRates;
+use crate::data::align::Alignment;
+use crate::series::{self, ChartLine, SeriesPoint, BTC_COLOR};
+
+// Loaded from the CDN unless a local copy is inlined with `with_plotly_js`
+const PLOTLY_CDN: &str = "https://cdn.plot.ly/plotly-2.35.2.min.js";
+
+/// An interactive Plotly page for one asset: the % change chart as drawn in the app
+/// (USD, BTC, each currency and compared baskets) above the raw USD prices
+pub struct HtmlReport<'a> {
+    data: &'a HashMap<Asset, Vec<PriceData>>,
+    fx: &'a ExchangeRates,
+    asset: Asset,
+    currencies: Vec<Currency>,
+    compared: Vec<Asset>,
+    mode: RepresentationMode,
+    alignment: Alignment,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    plotly_js: Option<String>,
+}
+
+impl<'a> HtmlReport<'a> {
+    pub fn new(data: &'a HashMap<Asset, Vec<PriceData>>, fx: &'a ExchangeRates, asset: Asset) -> Self {
+        Self {
+            data,
+            fx,
+            asset,
+            currencies: Vec::new(),
+            compared: Vec::new(),
+            mode: RepresentationMode::PricePerUnit,
+            alignment: Alignment::default(),
+            from: None,
+            to: None,
+            plotly_js: None,
+        }
+    }
+
+    pub fn with_currencies(mut self, currencies: &[Currency]) -> Self {
+        self.currencies = currencies.iter().copied().filter(|c| *c != Currency::USD).collect();
+        self
+    }
+
+    /// Baskets (or other assets) drawn as dashed Bitcoin lines next to the asset
+    pub fn with_compared(mut self, compared: &[Asset]) -> Self {
+        self.compared = compared.iter().copied().filter(|a| *a != self.asset).collect();
+        self
+    }
+
+    pub fn with_mode(mut self, mode: RepresentationMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// How asset prices are matched to Bitcoin dates; carried prices are marked on the lines
+    pub fn with_alignment(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    /// Dates to draw; % change is measured from the first point of each line on or after `from`
+    pub fn between(mut self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Self {
+        self.from = from;
+        self.to = to;
+        self
+    }
+
+    /// Inline this Plotly bundle instead of loading it from the CDN, for offline viewing
+    pub fn with_plotly_js(mut self, script: String) -> Self {
+        self.plotly_js = Some(script);
+        self
+    }
+
+    fn in_range(&self, points: Vec<SeriesPoint>) -> Vec<SeriesPoint> {
+        points.into_iter()
+            .filter(|(date, _)| series::in_range(*date, self.from, self.to))
+            .collect()
+    }
+
+    // Lines of the % change chart, as drawn in the app
+    fn change_traces(&self) -> Vec<Value> {
+        let lines = series::chart_lines(self.data, self.fx, self.asset, &self.currencies, &self.compared, self.mode, self.alignment);
+        let mut traces: Vec<Value> = lines.iter().map(|line| change_trace(line, self.from, self.to)).collect();
+        traces.extend(lines.iter().filter_map(|line| carried_trace(line, self.from, self.to)));
+        traces
+    }
+
+    // Raw USD prices, Bitcoin on the left axis and the asset on the right
+    fn price_traces(&self) -> Vec<Value> {
+        [(Asset::BITCOIN, BTC_COLOR, "y"), (self.asset, Currency::USD.color, "y2")]
+            .into_iter()
+            .map(|(asset, color, axis)| {
+                let points = self.in_range(self.data.get(&asset)
+                    .map(|d| d.iter().map(|p| (p.date, p.price_usd)).collect())
+                    .unwrap_or_default());
+                json!({
+                    "type": "scatter",
+                    "mode": "lines",
+                    "name": format!("{} (USD)", asset.base_name()),
+                    "x": dates(&points),
+                    "y": points.iter().map(|(_, v)| *v).collect::<Vec<_>>(),
+                    "yaxis": axis,
+                    "line": { "color": rgb(color), "width": 1.5 },
+                    "hovertemplate": "%{x|%Y-%m-%d}<br>$%{y:,.2f}<extra>%{fullData.name}</extra>",
+                })
+            })
+            .collect()
+    }
+
+    // Dashed vertical lines where Bitcoin prices switch from one source to the next
+    fn source_shapes(&self) -> Vec<Value> {
+        let ranges = self.data.get(&Asset::BITCOIN).map(|d| source_ranges(d)).unwrap_or_default();
+        ranges.iter()
+            .skip(1)
+            .filter(|(_, from, _)| series::in_range(*from, self.from, self.to))
+            .map(|(source, from, _)| json!({
+                "type": "line",
+                "xref": "x",
+                "yref": "paper",
+                "x0": from.to_string(),
+                "x1": from.to_string(),
+                "y0": 0,
+                "y1": 1,
+                "line": { "color": "gray", "width": 1, "dash": "dot" },
+                "label": { "text": format!("BTC source: {}", source.unwrap_or("derived")), "textposition": "end" },
+            }))
+            .collect()
+    }
+
+    pub fn render(&self) -> String {
+        let title = series::chart_title(self.asset, self.mode);
+        let change_layout = json!({
+            "title": { "text": format!("{} (% change from start date)", title) },
+            "hovermode": "x unified",
+            "xaxis": { "type": "date" },
+            "yaxis": { "ticksuffix": "%", "zeroline": true },
+            "shapes": self.source_shapes(),
+            "legend": { "orientation": "h" },
+        });
+        let price_layout = json!({
+            "title": { "text": "Prices in USD" },
+            "hovermode": "x unified",
+            "xaxis": { "type": "date" },
+            "yaxis": { "title": { "text": "Bitcoin" }, "type": "log", "tickprefix": "$" },
+            "yaxis2": { "title": { "text": self.asset.base_name() }, "overlaying": "y", "side": "right", "tickprefix": "$" },
+            "legend": { "orientation": "h" },
+        });
+
+        let plotly = match &self.plotly_js {
+            Some(script) => format!("<script>{}</script>", script),
+            None => format!("<script src=\"{}\"></script>", PLOTLY_CDN),
+        };
+        format!(
+            r#"<!doctype html>
+<html lang="en">
+<head>
+<meta charset="utf-8" />
+<title>{title}</title>
+{plotly}
+<style>body {{ font-family: sans-serif; margin: 1em; }} .chart {{ width: 100%; height: 600px; }}</style>
+</head>
+<body>
+<div id="change" class="chart"></div>
+<div id="prices" class="chart"></div>
+<p>Generated {generated}. Each line shows the % change from its first value on or after the start date; hover for the underlying value. Open circles mark days an asset did not trade and its last price was carried forward.</p>
+<script>
+const config = {{ responsive: true }};
+Plotly.newPlot("change", {change_data}, {change_layout}, config);
+Plotly.newPlot("prices", {price_data}, {price_layout}, config);
+</script>
+</body>
+</html>
+"#,
+            title = escape_html(&title),
+            plotly = plotly,
+            generated = chrono::Local::now().format("%Y-%m-%d %H:%M"),
+            change_data = script_json(&Value::Array(self.change_traces())),
+            change_layout = script_json(&change_layout),
+            price_data = script_json(&Value::Array(self.price_traces())),
+            price_layout = script_json(&price_layout),
+        )
+    }
+
+    /// Render to `path`, creating its directory if needed
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.render()).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+// A % change line; hovering shows the change and the value it was computed from
+fn change_trace(line: &ChartLine, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Value {
+    let changes = line.percent_change(from, to);
+    let values: Vec<f64> = line.points.iter()
+        .filter(|(date, _)| series::in_range(*date, from, to))
+        .map(|(_, v)| *v)
+        .collect();
+    json!({
+        "type": "scatter",
+        "mode": "lines",
+        "name": line.name,
+        "x": dates(&changes),
+        "y": changes.iter().map(|(_, v)| *v).collect::<Vec<_>>(),
+        "customdata": values,
+        "line": { "color": rgb(line.color), "width": line.width, "dash": if line.dashed { "dash" } else { "solid" } },
+        "hovertemplate": "%{y:+.2f}% (%{customdata:.6g})<extra>%{fullData.name}</extra>",
+    })
+}
+
+// Open circles on the days a line uses a price carried over a non-trading day
+fn carried_trace(line: &ChartLine, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Option<Value> {
+    let carried = line.carried_changes(from, to);
+    if carried.is_empty() {
+        return None;
+    }
+    Some(json!({
+        "type": "scatter",
+        "mode": "markers",
+        "name": format!("{} (carried, no trading)", line.name),
+        "x": dates(&carried),
+        "y": carried.iter().map(|(_, v)| *v).collect::<Vec<_>>(),
+        "marker": { "color": rgb(line.color), "symbol": "circle-open", "size": 5 },
+        "hovertemplate": "%{y:+.2f}% carried<extra>%{fullData.name}</extra>",
+    }))
+}
+
+fn dates(points: &[SeriesPoint]) -> Vec<String> {
+    points.iter().map(|(date, _)| date.format("%Y-%m-%d").to_string()).collect()
+}
+
+fn rgb((r, g, b): (u8, u8, u8)) -> String {
+    format!("rgb({},{},{})", r, g, b)
+}
+
+// JSON is valid JavaScript, but "</script>" inside a string would end the script tag
+fn script_json(value: &Value) -> String {
+    value.to_string().replace("</", "<\\/")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,298 @@
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use crate::models::{Asset, Currency, RepresentationMode};
+use crate::data::PriceData;
+use crate::data::fx::ExchangeRates;
+use crate::data::align::{Alignment, Calendar, DateIndex};
+
+/// Color of the Bitcoin line
+pub const BTC_COLOR: (u8, u8, u8) = (255, 165, 0);
+
+/// Line colors for baskets compared next to the selected asset
+pub const BASKET_COLORS: [(u8, u8, u8); 4] = [
+    (255, 210, 0),
+    (200, 90, 0),
+    (120, 200, 80),
+    (170, 120, 255),
+];
+
+/// One dated value of a purchasing-power series
+pub type SeriesPoint = (NaiveDate, f64);
+
+/// Whether `date` is within `from..=to`, either end being open when None
+pub fn in_range(date: NaiveDate, from: Option<NaiveDate>, to: Option<NaiveDate>) -> bool {
+    from.is_none_or(|f| date >= f) && to.is_none_or(|t| date <= t)
+}
+
+/// Asset priced in BTC (or BTC in units of the asset) on each Bitcoin date, with
+/// asset prices matched to Bitcoin dates by `alignment`
+pub fn btc_values(btc_data: &[PriceData], asset_data: &[PriceData], mode: RepresentationMode, alignment: Alignment) -> Vec<SeriesPoint> {
+    let prices = DateIndex::from_prices(asset_data);
+    btc_data.iter()
+        .zip(prices.join(btc_data.iter().map(|p| p.date), alignment))
+        .filter_map(|(btc_point, (_, price))| {
+            let price = price?;
+            let value = match mode {
+                RepresentationMode::UnitsPerCurrency => btc_point.price_usd / price,
+                RepresentationMode::PricePerUnit => price / btc_point.price_usd,
+            };
+            Some((btc_point.date, value))
+        })
+        .collect()
+}
+
+/// Bitcoin dates the asset did not trade on but was given its last traded price, so they
+/// can be marked; empty unless `alignment` carries prices forward (`Calendar::CarryForward`)
+pub fn carried_dates(btc_data: &[PriceData], asset_data: &[PriceData], alignment: Alignment) -> Vec<NaiveDate> {
+    if alignment.calendar != Calendar::CarryForward {
+        return Vec::new();
+    }
+    let prices = DateIndex::from_prices(asset_data);
+    prices.join(btc_data.iter().map(|p| p.date), alignment)
+        .filter(|(date, price)| price.is_some() && !prices.contains(*date))
+        .map(|(date, _)| date)
+        .collect()
+}
+
+// 1 on carried dates and 0 on the other dates of `points`, as an export column
+fn carried_flags(points: &[SeriesPoint], carried: &[NaiveDate]) -> Vec<SeriesPoint> {
+    let carried: HashSet<&NaiveDate> = carried.iter().collect();
+    points.iter()
+        .map(|(date, _)| (*date, if carried.contains(date) { 1.0 } else { 0.0 }))
+        .collect()
+}
+
+/// Asset priced in USD (or units of the asset per USD)
+pub fn usd_values(asset_data: &[PriceData], mode: RepresentationMode) -> Vec<SeriesPoint> {
+    asset_data.iter()
+        .map(|p| (p.date, per_mode(p.price_usd, mode)))
+        .collect()
+}
+
+/// Asset priced in `currency`, skipping dates without an exchange rate
+pub fn currency_values(asset_data: &[PriceData], fx: &ExchangeRates, currency: Currency, mode: RepresentationMode) -> Vec<SeriesPoint> {
+    let rates = fx.rates(currency, asset_data.iter().map(|p| p.date));
+    asset_data.iter()
+        .zip(rates)
+        .filter_map(|(p, rate)| Some((p.date, per_mode(p.price_usd * rate?, mode))))
+        .collect()
+}
+
+fn per_mode(price: f64, mode: RepresentationMode) -> f64 {
+    match mode {
+        RepresentationMode::UnitsPerCurrency => 1.0 / price,
+        RepresentationMode::PricePerUnit => price,
+    }
+}
+
+/// Percent change of each point from the first one
+pub fn percent_change(points: &[SeriesPoint]) -> Vec<SeriesPoint> {
+    let Some(&(_, base)) = points.first() else {
+        return Vec::new();
+    };
+    points.iter()
+        .map(|&(date, value)| (date, (value / base - 1.0) * 100.0))
+        .collect()
+}
+
+/// How the chart's values are computed and the dates they are shown for
+#[derive(Debug, Clone, Copy)]
+pub struct ChartOptions {
+    pub mode: RepresentationMode,
+    pub alignment: Alignment,
+    /// % change is measured from the first point on or after `from`
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+/// The numbers behind the % change chart for `asset` (plus `compared` baskets) within
+/// `options.from..=options.to`: raw USD prices, the BTC/USD/currency values and their %
+/// change, and with `Calendar::CarryForward` a 0/1 column per BTC line marking carried prices
+pub fn chart_table(
+    data: &HashMap<Asset, Vec<PriceData>>,
+    fx: &ExchangeRates,
+    asset: Asset,
+    currencies: &[Currency],
+    compared: &[Asset],
+    options: ChartOptions,
+) -> SeriesTable {
+    let ChartOptions { mode, alignment, from, to } = options;
+    let prefix = match mode {
+        RepresentationMode::PricePerUnit => "price",
+        RepresentationMode::UnitsPerCurrency => "units_per",
+    };
+    let since = |points: Vec<SeriesPoint>| -> Vec<SeriesPoint> {
+        points.into_iter().filter(|(date, _)| in_range(*date, from, to)).collect()
+    };
+    let raw = |asset: Asset| -> Vec<SeriesPoint> {
+        data.get(&asset).map(|d| d.iter().map(|p| (p.date, p.price_usd)).collect()).unwrap_or_default()
+    };
+    let empty = Vec::new();
+    let btc_data = data.get(&Asset::BITCOIN).unwrap_or(&empty);
+    let asset_data = data.get(&asset).unwrap_or(&empty);
+
+    let carry = alignment.calendar == Calendar::CarryForward;
+    let mut flags = Vec::new();
+    let mut btc_line = |name: String, asset_data: &[PriceData]| -> (String, Vec<SeriesPoint>) {
+        let points = btc_values(btc_data, asset_data, mode, alignment);
+        if carry {
+            let carried = carried_dates(btc_data, asset_data, alignment);
+            flags.push((format!("{}_carried", name), carried_flags(&points, &carried)));
+        }
+        (name, points)
+    };
+
+    let mut values = vec![btc_line(format!("{}_btc", prefix), asset_data)];
+    values.push((format!("{}_usd", prefix), usd_values(asset_data, mode)));
+    for &currency in currencies.iter().filter(|c| **c != Currency::USD) {
+        let points = currency_values(asset_data, fx, currency, mode);
+        values.push((format!("{}_{}", prefix, currency.code.to_ascii_lowercase()), points));
+    }
+    for &other in compared.iter().filter(|a| **a != asset) {
+        values.push(btc_line(format!("{}_{}_btc", other.id(), prefix), data.get(&other).unwrap_or(&empty)));
+    }
+
+    let mut columns = vec![
+        ("bitcoin_usd".to_string(), since(raw(Asset::BITCOIN))),
+        (format!("{}_usd", asset.id()), since(raw(asset))),
+    ];
+    let values: Vec<(String, Vec<SeriesPoint>)> = values.into_iter()
+        .map(|(name, points)| (name, since(points)))
+        .collect();
+    let changes: Vec<(String, Vec<SeriesPoint>)> = values.iter()
+        .map(|(name, points)| (format!("{}_pct", name), percent_change(points)))
+        .collect();
+    columns.extend(values);
+    columns.extend(changes);
+    columns.extend(flags.into_iter().map(|(name, points)| (name, since(points))));
+    SeriesTable::from_series(columns)
+}
+
+/// One line of the % change chart, with the values it is computed from
+#[derive(Debug, Clone)]
+pub struct ChartLine {
+    pub name: String,
+    pub color: (u8, u8, u8),
+    pub width: f64,
+    pub dashed: bool,
+    pub points: Vec<SeriesPoint>,
+    /// Dates whose value uses a price carried over a non-trading day
+    pub carried: Vec<NaiveDate>,
+}
+
+impl ChartLine {
+    /// Percent change from the first point on or after `from`, up to `to`
+    pub fn percent_change(&self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Vec<SeriesPoint> {
+        let visible: Vec<SeriesPoint> = self.points.iter()
+            .copied()
+            .filter(|(date, _)| in_range(*date, from, to))
+            .collect();
+        percent_change(&visible)
+    }
+
+    /// The points of `percent_change` on carried dates, for marking them
+    pub fn carried_changes(&self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Vec<SeriesPoint> {
+        if self.carried.is_empty() {
+            return Vec::new();
+        }
+        let carried: HashSet<&NaiveDate> = self.carried.iter().collect();
+        self.percent_change(from, to).into_iter()
+            .filter(|(date, _)| carried.contains(date))
+            .collect()
+    }
+}
+
+/// Chart heading for `asset` in `mode`
+pub fn chart_title(asset: Asset, mode: RepresentationMode) -> String {
+    match mode {
+        RepresentationMode::UnitsPerCurrency => format!("Purchasing power in {}", asset.name()),
+        RepresentationMode::PricePerUnit => format!("Price of {}", asset.name()),
+    }
+}
+
+/// The lines drawn in the app for `asset`: USD, BTC, each currency with exchange rates
+/// and a dashed Bitcoin line per compared basket, named like the chart legend. Bitcoin
+/// lines list their carried dates when `alignment` carries prices forward.
+pub fn chart_lines(
+    data: &HashMap<Asset, Vec<PriceData>>,
+    fx: &ExchangeRates,
+    asset: Asset,
+    currencies: &[Currency],
+    compared: &[Asset],
+    mode: RepresentationMode,
+    alignment: Alignment,
+) -> Vec<ChartLine> {
+    let unit = asset.unit();
+    let units = mode == RepresentationMode::UnitsPerCurrency;
+    let empty = Vec::new();
+    let btc_data = data.get(&Asset::BITCOIN).unwrap_or(&empty);
+    let asset_data = data.get(&asset).unwrap_or(&empty);
+    let line = |name: String, color, width, dashed, points| ChartLine { name, color, width, dashed, points, carried: Vec::new() };
+
+    let mut lines = vec![
+        line(if units { format!("{} per Dollar", unit) } else { format!("USD per {}", unit) },
+             Currency::USD.color, 2.5, false, usd_values(asset_data, mode)),
+        ChartLine {
+            carried: carried_dates(btc_data, asset_data, alignment),
+            ..line(if units { format!("{} per BTC", unit) } else { format!("BTC per {}", unit) },
+                   BTC_COLOR, 2.0, false, btc_values(btc_data, asset_data, mode, alignment))
+        },
+    ];
+    for &currency in currencies.iter().filter(|c| **c != Currency::USD && fx.has(**c)) {
+        lines.push(line(if units { format!("{} per {}", unit, currency.code) } else { format!("{} per {}", currency.code, unit) },
+                        currency.color, 1.5, false, currency_values(asset_data, fx, currency, mode)));
+    }
+    for (i, &other) in compared.iter().filter(|a| **a != asset).enumerate() {
+        let points = data.get(&other).map(|d| btc_values(btc_data, d, mode, alignment)).unwrap_or_default();
+        let carried = data.get(&other).map(|d| carried_dates(btc_data, d, alignment)).unwrap_or_default();
+        lines.push(ChartLine {
+            carried,
+            ..line(if units { format!("{} baskets per BTC", other.name()) } else { format!("BTC per {} basket", other.name()) },
+                   BASKET_COLORS[i % BASKET_COLORS.len()], 2.0, true, points)
+        });
+    }
+    lines
+}
+
+/// Several series joined on date, one optional value per column
+#[derive(Debug, Clone, Default)]
+pub struct SeriesTable {
+    pub columns: Vec<String>,
+    pub rows: BTreeMap<NaiveDate, Vec<Option<f64>>>,
+}
+
+impl SeriesTable {
+    pub fn from_series(series: Vec<(String, Vec<SeriesPoint>)>) -> Self {
+        let mut table = Self {
+            columns: series.iter().map(|(name, _)| name.clone()).collect(),
+            rows: BTreeMap::new(),
+        };
+        let width = table.columns.len();
+        for (column, (_, points)) in series.into_iter().enumerate() {
+            for (date, value) in points {
+                table.rows.entry(date).or_insert_with(|| vec![None; width])[column] = Some(value);
+            }
+        }
+        table
+    }
+
+    /// Keep only rows within `from..=to`
+    pub fn between(mut self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Self {
+        self.rows.retain(|date, _| in_range(*date, from, to));
+        self
+    }
+
+    /// Replace every column with its percent change from its first value in the table
+    pub fn percent_change(mut self) -> Self {
+        let mut bases: Vec<Option<f64>> = vec![None; self.columns.len()];
+        for values in self.rows.values_mut() {
+            for (value, base) in values.iter_mut().zip(bases.iter_mut()) {
+                if let Some(v) = value {
+                    let base = *base.get_or_insert(*v);
+                    *v = (*v / base - 1.0) * 100.0;
+                }
+            }
+        }
+        self
+    }
+}
@@ -0,0 +1,312 @@
+use anyhow::{Context, Result};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::{NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use crate::models::{Asset, Currency, RepresentationMode};
+use crate::data::{fetch_from_chain, source_ranges, LoadProgress, MarketData, Ohlcv};
+use crate::data::fx::ExchangeRates;
+use crate::data::cache::PriceCache;
+use crate::data::source::SourceChain;
+use crate::data::align::{AlignPolicy, Alignment, Calendar};
+use crate::data::resample::{self, Aggregation, Frequency};
+use crate::series::{self, in_range, SeriesPoint};
+
+// How often prices are reloaded while serving; only new days are fetched
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+type SharedData = Arc<RwLock<MarketData>>;
+
+/// Load prices through `cache`, then serve them on `addr` until the process is stopped,
+/// reloading every `REFRESH_INTERVAL`
+pub async fn serve(addr: SocketAddr, cache: PriceCache, currencies: Vec<Currency>) -> Result<()> {
+    let data: SharedData = Arc::new(RwLock::new(load(&cache, &currencies).await));
+    let listener = tokio::net::TcpListener::bind(addr).await
+        .with_context(|| format!("Failed to listen on {}", addr))?;
+    eprintln!("Serving on http://{}", addr);
+
+    let app = router(data.clone());
+    let mut server = tokio::spawn(async move { axum::serve(listener, app).await });
+
+    // Later loads only need the days since the last one
+    let cache = cache.for_refresh();
+    let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+    interval.tick().await;
+    loop {
+        tokio::select! {
+            result = &mut server => {
+                return result.context("Server task failed")?.context("Server failed");
+            }
+            _ = interval.tick() => {
+                let fresh = load(&cache, &currencies).await;
+                *data.write().unwrap() = fresh;
+            }
+        }
+    }
+}
+
+async fn load(cache: &PriceCache, currencies: &[Currency]) -> MarketData {
+    let start = Utc.with_ymd_and_hms(1999, 1, 1, 0, 0, 0).unwrap();
+    fetch_from_chain(&SourceChain::default(), Some(cache), currencies, start, Utc::now(), &|event| match event {
+        LoadProgress::Loaded { asset, data } => eprintln!("Loaded {:?}: {} points", asset, data.len()),
+        LoadProgress::Failed { asset, error } => eprintln!("Failed to load {:?}: {}", asset, error),
+        _ => {}
+    }).await
+}
+
+pub fn router(data: SharedData) -> Router {
+    Router::new()
+        .route("/assets", get(list_assets))
+        .route("/prices/:asset", get(prices))
+        .route("/purchasing-power/:asset", get(purchasing_power))
+        .with_state(data)
+}
+
+/// An error response: `{"error": "..."}` with a status code
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(serde_json::json!({ "error": self.1 }))).into_response()
+    }
+}
+
+fn bad_request(message: String) -> ApiError {
+    ApiError(StatusCode::BAD_REQUEST, message)
+}
+
+fn parse_asset(id: &str) -> Result<Asset, ApiError> {
+    Asset::from_id(id).ok_or_else(|| ApiError(StatusCode::NOT_FOUND, format!("Unknown asset {}", id)))
+}
+
+// `frequency` and `aggregate` query parameters, daily closes when missing
+fn parse_resampling(frequency: &Option<String>, aggregate: &Option<String>) -> Result<(Frequency, Aggregation), ApiError> {
+    let frequency = match frequency {
+        Some(name) => Frequency::parse(name)
+            .ok_or_else(|| bad_request(format!("Unknown frequency {}, expected daily, weekly, monthly, quarterly or yearly", name)))?,
+        None => Frequency::Daily,
+    };
+    let aggregation = match aggregate {
+        Some(name) => Aggregation::parse(name)
+            .ok_or_else(|| bad_request(format!("Unknown aggregate {}, expected last, mean or ohlc", name)))?,
+        None => Aggregation::Last,
+    };
+    Ok((frequency, aggregation))
+}
+
+// Rates resampled like the prices of a request; daily rates are used without copying them
+fn rates_at(fx: &ExchangeRates, frequency: Frequency, aggregation: Aggregation) -> Cow<'_, ExchangeRates> {
+    if frequency == Frequency::Daily {
+        Cow::Borrowed(fx)
+    } else {
+        Cow::Owned(fx.resampled(frequency, aggregation))
+    }
+}
+
+#[derive(Serialize)]
+struct AssetInfo {
+    id: &'static str,
+    name: &'static str,
+    unit: &'static str,
+    category: &'static str,
+    currency: &'static str,
+    basket: bool,
+    points: usize,
+    first: Option<NaiveDate>,
+    last: Option<NaiveDate>,
+    sources: Vec<&'static str>,
+}
+
+// GET /assets: every asset and basket with the range of prices loaded for it
+async fn list_assets(State(data): State<SharedData>) -> Json<Vec<AssetInfo>> {
+    let data = data.read().unwrap();
+    let assets = Asset::tracked().iter().copied().chain(Asset::baskets())
+        .map(|asset| {
+            let prices = data.prices.get(&asset).map(Vec::as_slice).unwrap_or_default();
+            AssetInfo {
+                id: asset.id(),
+                name: asset.name(),
+                unit: asset.unit(),
+                category: asset.category(),
+                currency: asset.quote_currency().code,
+                basket: asset.is_basket(),
+                points: prices.len(),
+                first: prices.first().map(|p| p.date),
+                last: prices.last().map(|p| p.date),
+                sources: source_ranges(prices).into_iter().map(|(source, _, _)| source.unwrap_or("derived")).collect(),
+            }
+        })
+        .collect();
+    Json(assets)
+}
+
+#[derive(Deserialize)]
+struct PriceQuery {
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    currency: Option<String>,
+    frequency: Option<String>,
+    aggregate: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PricePoint {
+    date: NaiveDate,
+    price: f64,
+    source: Option<&'static str>,
+    /// Open/high/low/close in USD, with `aggregate=ohlc` for sources that have bars
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bar: Option<Ohlcv>,
+}
+
+#[derive(Serialize)]
+struct PriceResponse {
+    asset: &'static str,
+    currency: &'static str,
+    prices: Vec<PricePoint>,
+}
+
+// GET /prices/{asset}?from=&to=&currency=&frequency=&aggregate=: closes (daily unless
+// `frequency` is given), in USD unless `currency` is given
+async fn prices(
+    State(data): State<SharedData>,
+    Path(id): Path<String>,
+    Query(query): Query<PriceQuery>,
+) -> Result<Json<PriceResponse>, ApiError> {
+    let asset = parse_asset(&id)?;
+    let currency = match &query.currency {
+        Some(code) => Currency::from_code(code).ok_or_else(|| bad_request(format!("Unknown currency {}", code)))?,
+        None => Currency::USD,
+    };
+    let (frequency, aggregation) = parse_resampling(&query.frequency, &query.aggregate)?;
+    let data = data.read().unwrap();
+    if !data.fx.has(currency) {
+        return Err(bad_request(format!("No {} exchange rates loaded, start the server with --currencies {}", currency.code, currency.code)));
+    }
+    let asset_data = data.prices.get(&asset)
+        .ok_or_else(|| ApiError(StatusCode::NOT_FOUND, format!("No prices loaded for {}", id)))?;
+    let fx = rates_at(&data.fx, frequency, aggregation);
+    let prices = resample::resample(asset_data, frequency, aggregation)
+        .into_iter()
+        .filter(|p| in_range(p.date, query.from, query.to))
+        .filter_map(|p| {
+            let price = fx.convert(p.price_usd, currency, p.date)?;
+            Some(PricePoint { date: p.date, price, source: p.source, bar: p.ohlcv })
+        })
+        .collect();
+    Ok(Json(PriceResponse { asset: asset.id(), currency: currency.code, prices }))
+}
+
+#[derive(Deserialize)]
+struct PurchasingPowerQuery {
+    mode: Option<String>,
+    base: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    currencies: Option<String>,
+    align: Option<String>,
+    max_days: Option<i64>,
+    calendar: Option<String>,
+    frequency: Option<String>,
+    aggregate: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ValuePoint {
+    date: NaiveDate,
+    value: f64,
+    change_pct: f64,
+    /// Uses a price carried over a day the asset did not trade, only with `calendar=carry`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    carried: bool,
+}
+
+#[derive(Serialize)]
+struct PurchasingPowerSeries {
+    against: &'static str,
+    values: Vec<ValuePoint>,
+}
+
+#[derive(Serialize)]
+struct PurchasingPowerResponse {
+    asset: &'static str,
+    mode: String,
+    base: Option<NaiveDate>,
+    series: Vec<PurchasingPowerSeries>,
+}
+
+// Values from `base` on with their % change from the first one, like the chart
+fn value_points(points: Vec<SeriesPoint>, carried: &[NaiveDate], base: Option<NaiveDate>, to: Option<NaiveDate>) -> Vec<ValuePoint> {
+    let points: Vec<SeriesPoint> = points.into_iter().filter(|(date, _)| in_range(*date, base, to)).collect();
+    points.iter()
+        .zip(series::percent_change(&points))
+        .map(|(&(date, value), (_, change_pct))| {
+            let carried = carried.binary_search(&date).is_ok();
+            ValuePoint { date, value, change_pct, carried }
+        })
+        .collect()
+}
+
+// GET /purchasing-power/{asset}?mode=&base=&to=&currencies=&align=&max_days=&calendar=&frequency=&aggregate=:
+// the asset in BTC, USD and each requested currency, as drawn in the chart
+async fn purchasing_power(
+    State(data): State<SharedData>,
+    Path(id): Path<String>,
+    Query(query): Query<PurchasingPowerQuery>,
+) -> Result<Json<PurchasingPowerResponse>, ApiError> {
+    let asset = parse_asset(&id)?;
+    let mode = match &query.mode {
+        Some(name) => RepresentationMode::parse(name)
+            .ok_or_else(|| bad_request(format!("Unknown mode {}, expected UnitsPerCurrency or PricePerUnit", name)))?,
+        None => RepresentationMode::PricePerUnit,
+    };
+    let mut alignment = Alignment::default();
+    if let Some(name) = &query.align {
+        alignment.policy = AlignPolicy::parse(name)
+            .ok_or_else(|| bad_request(format!("Unknown align {}, expected exact, ffill, bfill, nearest or linear", name)))?;
+    }
+    if let Some(days) = query.max_days {
+        if days < 0 {
+            return Err(bad_request(format!("Invalid max_days {}, expected 0 or more days", days)));
+        }
+        alignment.max_days = days;
+    }
+    if let Some(name) = &query.calendar {
+        alignment.calendar = Calendar::parse(name)
+            .ok_or_else(|| bad_request(format!("Unknown calendar {}, expected all, trading or carry", name)))?;
+    }
+    let (frequency, aggregation) = parse_resampling(&query.frequency, &query.aggregate)?;
+    let data = data.read().unwrap();
+    let currencies: Vec<Currency> = match &query.currencies {
+        Some(list) => Currency::parse_list(list),
+        None => Currency::all().iter().copied().filter(|c| *c != Currency::USD && data.fx.has(*c)).collect(),
+    };
+    if let Some(missing) = currencies.iter().find(|c| !data.fx.has(**c)) {
+        return Err(bad_request(format!("No {} exchange rates loaded", missing.code)));
+    }
+    let asset_data = data.prices.get(&asset)
+        .ok_or_else(|| ApiError(StatusCode::NOT_FOUND, format!("No prices loaded for {}", id)))?;
+    let btc_data = data.prices.get(&Asset::BITCOIN)
+        .ok_or_else(|| ApiError(StatusCode::SERVICE_UNAVAILABLE, "No Bitcoin prices loaded".to_string()))?;
+    let asset_data = &resample::resample(asset_data, frequency, aggregation);
+    let btc_data = &resample::resample(btc_data, frequency, aggregation);
+    let fx = &rates_at(&data.fx, frequency, aggregation);
+
+    let mut lines = vec![
+        ("BTC", series::btc_values(btc_data, asset_data, mode, alignment), series::carried_dates(btc_data, asset_data, alignment)),
+        ("USD", series::usd_values(asset_data, mode), Vec::new()),
+    ];
+    for &currency in &currencies {
+        lines.push((currency.code, series::currency_values(asset_data, fx, currency, mode), Vec::new()));
+    }
+    let series = lines.into_iter()
+        .map(|(against, points, carried)| PurchasingPowerSeries { against, values: value_points(points, &carried, query.base, query.to) })
+        .collect();
+    Ok(Json(PurchasingPowerResponse { asset: asset.id(), mode: format!("{:?}", mode), base: query.base, series }))
+}
@@ -0,0 +1,506 @@
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::HashMap;
+use crate::models::{Asset, Currency, RepresentationMode};
+use crate::data::PriceData;
+use crate::data::fx::ExchangeRates;
+use crate::data::align::{Alignment, DateIndex};
+use crate::series::{self, SeriesPoint};
+
+const DAYS_PER_YEAR: f64 = 365.25;
+
+/// Performance of holding one unit of a currency (or BTC), measured in units of an asset.
+/// Percentages are in percent; annualized figures assume as many periods per year as the
+/// series has on average, so they work for daily and resampled series alike.
+#[derive(Debug, Clone, Serialize)]
+pub struct LineStats {
+    pub line: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub total_return_pct: f64,
+    pub cagr_pct: Option<f64>,
+    pub volatility_pct: Option<f64>,
+    pub max_drawdown_pct: f64,
+    pub drawdown_peak: NaiveDate,
+    pub drawdown_trough: NaiveDate,
+    /// Largest rise and fall from one point to the next, dated on the later point
+    pub best_period_pct: Option<f64>,
+    pub best_period: Option<NaiveDate>,
+    pub worst_period_pct: Option<f64>,
+    pub worst_period: Option<NaiveDate>,
+    /// Annualized mean log return over annualized volatility, with no risk-free rate
+    pub sharpe: Option<f64>,
+}
+
+impl LineStats {
+    /// Statistics of `points` (sorted by date, positive values); None with fewer than two points
+    pub fn new(line: &str, points: &[SeriesPoint]) -> Option<Self> {
+        let (&(start, first), &(end, last)) = (points.first()?, points.last()?);
+        if points.len() < 2 || first <= 0.0 {
+            return None;
+        }
+        let years = (end - start).num_days() as f64 / DAYS_PER_YEAR;
+        let cagr_pct = (years > 0.0).then(|| ((last / first).powf(1.0 / years) - 1.0) * 100.0);
+
+        // Peak-to-trough fall of the running maximum
+        let (mut peak, mut peak_date) = (first, start);
+        let (mut max_drawdown, mut drawdown_peak, mut drawdown_trough) = (0.0, start, start);
+        for &(date, value) in points {
+            if value > peak {
+                peak = value;
+                peak_date = date;
+            }
+            let drawdown = value / peak - 1.0;
+            if drawdown < max_drawdown {
+                max_drawdown = drawdown;
+                drawdown_peak = peak_date;
+                drawdown_trough = date;
+            }
+        }
+
+        let returns: Vec<(NaiveDate, f64)> = points.windows(2)
+            .filter(|pair| pair[0].1 > 0.0 && pair[1].1 > 0.0)
+            .map(|pair| (pair[1].0, (pair[1].1 / pair[0].1).ln()))
+            .collect();
+        let best = returns.iter().copied().max_by(|a, b| a.1.total_cmp(&b.1));
+        let worst = returns.iter().copied().min_by(|a, b| a.1.total_cmp(&b.1));
+
+        let (volatility, annual_return) = if returns.len() >= 2 && years > 0.0 {
+            let per_year = returns.len() as f64 / years;
+            let mean = returns.iter().map(|(_, r)| r).sum::<f64>() / returns.len() as f64;
+            let variance = returns.iter().map(|(_, r)| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+            (Some(variance.sqrt() * per_year.sqrt()), Some(mean * per_year))
+        } else {
+            (None, None)
+        };
+        let sharpe = match (annual_return, volatility) {
+            (Some(annual_return), Some(volatility)) if volatility > 0.0 => Some(annual_return / volatility),
+            _ => None,
+        };
+
+        let pct = |log_return: f64| (log_return.exp() - 1.0) * 100.0;
+        Some(Self {
+            line: line.to_string(),
+            start,
+            end,
+            total_return_pct: (last / first - 1.0) * 100.0,
+            cagr_pct,
+            volatility_pct: volatility.map(|v| v * 100.0),
+            max_drawdown_pct: max_drawdown * 100.0,
+            drawdown_peak,
+            drawdown_trough,
+            best_period_pct: best.map(|(_, r)| pct(r)),
+            best_period: best.map(|(date, _)| date),
+            worst_period_pct: worst.map(|(_, r)| pct(r)),
+            worst_period: worst.map(|(date, _)| date),
+            sharpe,
+        })
+    }
+}
+
+/// Statistics of BTC, USD and each currency in `from..=to`, each measured in units of
+/// `asset` (the purchasing power of one BTC, dollar, ...)
+pub fn purchasing_power_stats(
+    data: &HashMap<Asset, Vec<PriceData>>,
+    fx: &ExchangeRates,
+    asset: Asset,
+    currencies: &[Currency],
+    alignment: Alignment,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Vec<LineStats> {
+    let mode = RepresentationMode::UnitsPerCurrency;
+    let empty = Vec::new();
+    let btc_data = data.get(&Asset::BITCOIN).unwrap_or(&empty);
+    let asset_data = data.get(&asset).unwrap_or(&empty);
+
+    let mut lines = vec![
+        ("BTC", series::btc_values(btc_data, asset_data, mode, alignment)),
+        ("USD", series::usd_values(asset_data, mode)),
+    ];
+    for &currency in currencies.iter().filter(|c| **c != Currency::USD && fx.has(**c)) {
+        lines.push((currency.code, series::currency_values(asset_data, fx, currency, mode)));
+    }
+    lines.into_iter()
+        .filter_map(|(line, points)| {
+            let visible: Vec<SeriesPoint> = points.into_iter()
+                .filter(|(date, _)| series::in_range(*date, from, to))
+                .collect();
+            LineStats::new(line, &visible)
+        })
+        .collect()
+}
+
+/// Windows offered for rolling statistics, in days
+pub const ROLLING_WINDOWS: [i64; 3] = [30, 90, 365];
+
+/// Bitcoin against an asset over the `window` days up to `date`, from the log returns of
+/// both between the dates they are aligned on
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct RollingPoint {
+    pub date: NaiveDate,
+    pub correlation: Option<f64>,
+    /// How much Bitcoin's return moves per unit of the asset's return
+    pub beta: Option<f64>,
+    /// Annualized volatility of the BTC/asset price ratio, in percent
+    pub ratio_volatility_pct: Option<f64>,
+}
+
+/// One of the values of a `RollingPoint`, for drawing them one at a time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RollingMetric {
+    Correlation,
+    Beta,
+    RatioVolatility,
+}
+
+impl RollingMetric {
+    pub fn all() -> [RollingMetric; 3] {
+        [RollingMetric::Correlation, RollingMetric::Beta, RollingMetric::RatioVolatility]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Correlation => "Correlation",
+            Self::Beta => "Beta",
+            Self::RatioVolatility => "Ratio volatility",
+        }
+    }
+
+    pub fn value(&self, point: &RollingPoint) -> Option<f64> {
+        match self {
+            Self::Correlation => point.correlation,
+            Self::Beta => point.beta,
+            Self::RatioVolatility => point.ratio_volatility_pct,
+        }
+    }
+}
+
+/// `RollingPoint` on every Bitcoin date with a full `window` of history before it; asset
+/// prices are matched to Bitcoin dates by `alignment`, so carried-forward prices count as
+/// unchanged days unless the calendar keeps trading days only
+pub fn rolling(btc_data: &[PriceData], asset_data: &[PriceData], alignment: Alignment, window: i64) -> Vec<RollingPoint> {
+    let prices = DateIndex::from_prices(asset_data);
+    let aligned: Vec<(NaiveDate, f64, f64)> = btc_data.iter()
+        .zip(prices.join(btc_data.iter().map(|p| p.date), alignment))
+        .filter_map(|(btc, (date, price))| Some((date, btc.price_usd, price?)))
+        .filter(|(_, btc, price)| *btc > 0.0 && *price > 0.0)
+        .collect();
+    let Some(&(first_date, _, _)) = aligned.first() else {
+        return Vec::new();
+    };
+    // (date, Bitcoin log return, asset log return)
+    let returns: Vec<(NaiveDate, f64, f64)> = aligned.windows(2)
+        .map(|pair| (pair[1].0, (pair[1].1 / pair[0].1).ln(), (pair[1].2 / pair[0].2).ln()))
+        .collect();
+
+    // Running sums over the window, added at the front and removed at the back
+    let mut sums = WindowSums::default();
+    let mut oldest = 0;
+    let mut points = Vec::new();
+    for &(date, btc, asset) in &returns {
+        sums.add(btc, asset, 1.0);
+        while returns[oldest].0 <= date - chrono::Duration::days(window) {
+            let (_, btc, asset) = returns[oldest];
+            sums.add(btc, asset, -1.0);
+            oldest += 1;
+        }
+        if date - chrono::Duration::days(window) < first_date {
+            continue;
+        }
+        points.push(sums.point(date, window));
+    }
+    points
+}
+
+#[derive(Default)]
+struct WindowSums {
+    n: f64,
+    btc: f64,
+    asset: f64,
+    btc_sq: f64,
+    asset_sq: f64,
+    product: f64,
+    ratio: f64,
+    ratio_sq: f64,
+}
+
+impl WindowSums {
+    // Add a pair of returns with `sign` 1, or remove it with -1
+    fn add(&mut self, btc: f64, asset: f64, sign: f64) {
+        self.n += sign;
+        self.btc += sign * btc;
+        self.asset += sign * asset;
+        self.btc_sq += sign * btc * btc;
+        self.asset_sq += sign * asset * asset;
+        self.product += sign * btc * asset;
+        self.ratio += sign * (btc - asset);
+        self.ratio_sq += sign * (btc - asset) * (btc - asset);
+    }
+
+    fn point(&self, date: NaiveDate, window: i64) -> RollingPoint {
+        if self.n < 2.0 {
+            return RollingPoint { date, correlation: None, beta: None, ratio_volatility_pct: None };
+        }
+        // Sample (co)variances; clamped at 0 against rounding in the running sums
+        let covariance = |sum_xy: f64, sum_x: f64, sum_y: f64| (sum_xy - sum_x * sum_y / self.n) / (self.n - 1.0);
+        let btc_var = covariance(self.btc_sq, self.btc, self.btc).max(0.0);
+        let asset_var = covariance(self.asset_sq, self.asset, self.asset).max(0.0);
+        let cov = covariance(self.product, self.btc, self.asset);
+        let ratio_var = covariance(self.ratio_sq, self.ratio, self.ratio).max(0.0);
+        let per_year = self.n * DAYS_PER_YEAR / window as f64;
+        RollingPoint {
+            date,
+            correlation: (btc_var > 0.0 && asset_var > 0.0).then(|| (cov / (btc_var * asset_var).sqrt()).clamp(-1.0, 1.0)),
+            beta: (asset_var > 0.0).then(|| cov / asset_var),
+            ratio_volatility_pct: Some((ratio_var * per_year).sqrt() * 100.0),
+        }
+    }
+}
+
+// Fewer common returns than this give no correlation
+const MIN_COMMON_RETURNS: usize = 5;
+
+/// Pairwise correlation of log returns, each pair over the dates both assets have prices
+/// on, so 24/7 and exchange-traded series are compared over the same intervals
+#[derive(Debug, Clone)]
+pub struct CorrelationMatrix {
+    pub assets: Vec<Asset>,
+    /// `values[i][j]` between `assets[i]` and `assets[j]`; None with too few common returns
+    pub values: Vec<Vec<Option<f64>>>,
+    /// Returns each value was computed from
+    pub counts: Vec<Vec<usize>>,
+}
+
+impl CorrelationMatrix {
+    /// Correlations of every asset in `assets` that has prices in `data` between `from` and `to`
+    pub fn new(data: &HashMap<Asset, Vec<PriceData>>, assets: &[Asset], from: Option<NaiveDate>, to: Option<NaiveDate>) -> Self {
+        let series: Vec<(Asset, Vec<(NaiveDate, f64)>)> = assets.iter()
+            .filter_map(|asset| {
+                let prices: Vec<(NaiveDate, f64)> = data.get(asset)?.iter()
+                    .filter(|p| p.price_usd > 0.0)
+                    .filter(|p| series::in_range(p.date, from, to))
+                    .map(|p| (p.date, p.price_usd))
+                    .collect();
+                (prices.len() > MIN_COMMON_RETURNS).then_some((*asset, prices))
+            })
+            .collect();
+        let n = series.len();
+        let mut values = vec![vec![None; n]; n];
+        let mut counts = vec![vec![0; n]; n];
+        for i in 0..n {
+            for j in i..n {
+                let (value, count) = pair_correlation(&series[i].1, &series[j].1);
+                values[i][j] = value;
+                values[j][i] = value;
+                counts[i][j] = count;
+                counts[j][i] = count;
+            }
+        }
+        Self { assets: series.into_iter().map(|(asset, _)| asset).collect(), values, counts }
+    }
+}
+
+// Correlation of the returns of two sorted price series between their common dates
+fn pair_correlation(a: &[(NaiveDate, f64)], b: &[(NaiveDate, f64)]) -> (Option<f64>, usize) {
+    let mut common = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].0.cmp(&b[j].0) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                common.push((a[i].1, b[j].1));
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    let returns: Vec<(f64, f64)> = common.windows(2)
+        .map(|pair| ((pair[1].0 / pair[0].0).ln(), (pair[1].1 / pair[0].1).ln()))
+        .collect();
+    if returns.len() < MIN_COMMON_RETURNS {
+        return (None, returns.len());
+    }
+    let count = returns.len() as f64;
+    let mean_a = returns.iter().map(|r| r.0).sum::<f64>() / count;
+    let mean_b = returns.iter().map(|r| r.1).sum::<f64>() / count;
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (ra, rb) in &returns {
+        cov += (ra - mean_a) * (rb - mean_b);
+        var_a += (ra - mean_a).powi(2);
+        var_b += (rb - mean_b).powi(2);
+    }
+    let value = (var_a > 0.0 && var_b > 0.0).then(|| (cov / (var_a * var_b).sqrt()).clamp(-1.0, 1.0));
+    (value, returns.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Datelike;
+    use crate::data::align::AlignPolicy;
+    use crate::data::fixtures::{date, prices};
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn line_stats_need_two_points() {
+        assert!(LineStats::new("BTC", &[]).is_none());
+        assert!(LineStats::new("BTC", &[(date(2024, 1, 1), 1.0)]).is_none());
+    }
+
+    #[test]
+    fn line_stats_drawdown_and_short_cagr() {
+        let points = [
+            (date(2024, 1, 1), 100.0),
+            (date(2024, 2, 1), 150.0),
+            (date(2024, 3, 1), 90.0),
+            (date(2024, 4, 1), 120.0),
+        ];
+        let stats = LineStats::new("BTC", &points).unwrap();
+        assert!(close(stats.total_return_pct, 20.0));
+        assert!(close(stats.max_drawdown_pct, -40.0));
+        assert_eq!((stats.drawdown_peak, stats.drawdown_trough), (date(2024, 2, 1), date(2024, 3, 1)));
+        // 20% over 91 days compounds to far more than 20% a year
+        let years = 91.0 / DAYS_PER_YEAR;
+        assert!(close(stats.cagr_pct.unwrap(), (1.2f64.powf(1.0 / years) - 1.0) * 100.0));
+        assert!(stats.cagr_pct.unwrap() > 100.0);
+        assert_eq!(stats.best_period, Some(date(2024, 2, 1)));
+        assert!(close(stats.best_period_pct.unwrap(), 50.0));
+        assert_eq!(stats.worst_period, Some(date(2024, 3, 1)));
+        assert!(close(stats.worst_period_pct.unwrap(), -40.0));
+    }
+
+    #[test]
+    fn line_stats_without_a_fall_have_no_drawdown() {
+        let points = [(date(2024, 1, 1), 1.0), (date(2024, 1, 2), 2.0)];
+        let stats = LineStats::new("BTC", &points).unwrap();
+        assert_eq!(stats.max_drawdown_pct, 0.0);
+        assert_eq!((stats.drawdown_peak, stats.drawdown_trough), (date(2024, 1, 1), date(2024, 1, 1)));
+    }
+
+    // Bitcoin every day, the asset only on weekdays, with unrelated wiggles
+    fn rolling_inputs() -> (Vec<PriceData>, Vec<PriceData>) {
+        let days = || (0..120).map(|i| date(2024, 1, 1) + chrono::Duration::days(i));
+        let btc = prices(days(), |i| 100.0 * (0.01 * i + 0.05 * (1.3 * i).sin()).exp());
+        let asset = prices(days().filter(|d| d.weekday().number_from_monday() <= 5),
+                           |i| 50.0 * (0.002 * i + 0.03 * (0.7 * i).cos() + 0.02 * (2.9 * i).sin()).exp());
+        (btc, asset)
+    }
+
+    // Statistics of the returns dated within `window` days up to `date`, computed directly
+    fn direct(returns: &[(NaiveDate, f64, f64)], date: NaiveDate, window: i64) -> RollingPoint {
+        let in_window: Vec<(f64, f64)> = returns.iter()
+            .filter(|(d, _, _)| *d <= date && *d > date - chrono::Duration::days(window))
+            .map(|(_, btc, asset)| (*btc, *asset))
+            .collect();
+        let n = in_window.len() as f64;
+        let mean = |f: &dyn Fn(&(f64, f64)) -> f64| in_window.iter().map(f).sum::<f64>() / n;
+        let (btc_mean, asset_mean) = (mean(&|r| r.0), mean(&|r| r.1));
+        let ratio_mean = btc_mean - asset_mean;
+        let sum = |f: &dyn Fn(&(f64, f64)) -> f64| in_window.iter().map(f).sum::<f64>() / (n - 1.0);
+        let btc_var = sum(&|r| (r.0 - btc_mean).powi(2));
+        let asset_var = sum(&|r| (r.1 - asset_mean).powi(2));
+        let cov = sum(&|r| (r.0 - btc_mean) * (r.1 - asset_mean));
+        let ratio_var = sum(&|r| (r.0 - r.1 - ratio_mean).powi(2));
+        RollingPoint {
+            date,
+            correlation: Some(cov / (btc_var * asset_var).sqrt()),
+            beta: Some(cov / asset_var),
+            ratio_volatility_pct: Some((ratio_var * n * DAYS_PER_YEAR / window as f64).sqrt() * 100.0),
+        }
+    }
+
+    #[test]
+    fn rolling_matches_direct_computation() {
+        let (btc, asset) = rolling_inputs();
+        let alignment = Alignment::new(AlignPolicy::Exact, 0);
+        // Returns between the weekdays both series have prices on
+        let aligned: Vec<(NaiveDate, f64, f64)> = asset.iter()
+            .map(|a| (a.date, btc.iter().find(|b| b.date == a.date).unwrap().price_usd, a.price_usd))
+            .collect();
+        let returns: Vec<(NaiveDate, f64, f64)> = aligned.windows(2)
+            .map(|pair| (pair[1].0, (pair[1].1 / pair[0].1).ln(), (pair[1].2 / pair[0].2).ln()))
+            .collect();
+
+        for window in [7, 30, 90] {
+            let points = rolling(&btc, &asset, alignment, window);
+            assert!(!points.is_empty());
+            // The first point has a full window of history behind it
+            assert!(points[0].date - chrono::Duration::days(window) >= aligned[0].0);
+            for point in &points {
+                let expected = direct(&returns, point.date, window);
+                for (got, want) in [
+                    (point.correlation, expected.correlation),
+                    (point.beta, expected.beta),
+                    (point.ratio_volatility_pct, expected.ratio_volatility_pct),
+                ] {
+                    let (got, want) = (got.unwrap(), want.unwrap());
+                    assert!((got - want).abs() < 1e-9 * want.abs().max(1.0), "{} day window on {}: {:?} != {:?}",
+                            window, point.date, got, want);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rolling_window_longer_than_series_is_empty() {
+        let (btc, asset) = rolling_inputs();
+        assert!(rolling(&btc, &asset, Alignment::default(), 365).is_empty());
+    }
+
+    #[test]
+    fn rolling_beta_of_squared_series_is_two() {
+        let (btc, _) = rolling_inputs();
+        let asset = prices(btc.iter().map(|p| p.date), |i| btc[i as usize].price_usd.sqrt());
+        for point in rolling(&btc, &asset, Alignment::default(), 30) {
+            assert!(close(point.beta.unwrap(), 2.0));
+            assert!(close(point.correlation.unwrap(), 1.0));
+        }
+    }
+
+    fn matrix_of(series: [(&str, &dyn Fn(f64) -> f64); 3]) -> CorrelationMatrix {
+        let assets: Vec<Asset> = series.iter().map(|(id, _)| Asset::from_id(id).unwrap()).collect();
+        let days = || (0..40).map(|i| date(2024, 1, 1) + chrono::Duration::days(i));
+        let data = assets.iter().zip(&series)
+            .map(|(asset, (_, price))| (*asset, prices(days(), price)))
+            .collect();
+        CorrelationMatrix::new(&data, &assets, None, None)
+    }
+
+    #[test]
+    fn correlation_of_powers_is_plus_or_minus_one() {
+        let base = |i: f64| 100.0 * (0.01 * i + 0.1 * (1.7 * i).sin()).exp();
+        let matrix = matrix_of([
+            ("bitcoin", &base),
+            ("gold", &|i| base(i).powi(2)),
+            ("silver", &|i| 1.0 / base(i)),
+        ]);
+        assert_eq!(matrix.assets.len(), 3);
+        assert!(close(matrix.values[0][0].unwrap(), 1.0));
+        assert!(close(matrix.values[0][1].unwrap(), 1.0));
+        assert!(close(matrix.values[0][2].unwrap(), -1.0));
+        assert!(close(matrix.values[1][2].unwrap(), -1.0));
+        assert_eq!(matrix.values[2][1], matrix.values[1][2]);
+        assert_eq!(matrix.counts[0][1], 39);
+    }
+
+    #[test]
+    fn correlation_with_constant_series_is_none() {
+        let base = |i: f64| 100.0 + (1.7 * i).sin();
+        let matrix = matrix_of([
+            ("bitcoin", &base),
+            ("gold", &|_| 2000.0),
+            ("silver", &|i| base(i) * 3.0),
+        ]);
+        assert_eq!(matrix.values[0][1], None);
+        assert_eq!(matrix.values[1][1], None);
+        assert_eq!(matrix.values[1][2], None);
+        assert!(close(matrix.values[0][2].unwrap(), 1.0));
+        // The returns are still counted
+        assert_eq!(matrix.counts[0][1], 39);
+    }
+}